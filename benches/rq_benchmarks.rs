@@ -1,6 +1,6 @@
 use criterion::{criterion_group, criterion_main, Criterion, BenchmarkGroup, measurement::WallTime};
 use rand::{Rng, rngs::OsRng};
-use rq_library::processor::{ProcessorConfig, RaptorQProcessor};
+use rq_library::processor::{ProcessorConfig, RaptorQProcessor, SymbolStorage};
 use std::fs::{self, File};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
@@ -650,6 +650,64 @@ fn bench_create_metadata_1gb(group: &mut BenchmarkGroup<WallTime>) {
     println!("Average bytes allocated: {}; Average number of allocations: {}", bytes_to_mb_or_gb(total_bytes / counter), total_allocations / counter);
 }
 
+// Benchmark writing the symbols of a 10MB file one file per symbol vs. into a single append log
+fn bench_symbol_storage_10mb(group: &mut BenchmarkGroup<WallTime>) {
+    // Small symbols produce many symbol files, which is where the append log pays off
+    let config = ProcessorConfig {
+        symbol_size: 1024,
+        ..ProcessorConfig::default()
+    };
+    let processor = RaptorQProcessor::new(config);
+
+    for (name, storage) in [("symbols_as_files_10mb", SymbolStorage::Files), ("symbols_append_log_10mb", SymbolStorage::AppendLog)] {
+        group.bench_function(name, |b| {
+            // Set up environment fresh for each iteration
+            let (temp_dir, input_file, output_dir) = setup_test_env(SIZE_10MB);
+
+            b.iter(|| {
+                processor
+                    .encode_file_with_storage(
+                        input_file.to_str().unwrap(),
+                        output_dir.to_str().unwrap(),
+                        0, // Let the processor determine block size
+                        false,
+                        storage,
+                    )
+                    .expect("Failed to encode file");
+            });
+
+            // Keep temp_dir in scope until benchmark is done
+            drop(temp_dir);
+        });
+    }
+}
+
+// Benchmark decoding a 1MB file split into thousands of small symbol files, where
+// locating and opening each symbol dominates over the RaptorQ decoding itself
+fn bench_decode_many_symbols_1mb(group: &mut BenchmarkGroup<WallTime>) {
+    let config = ProcessorConfig {
+        symbol_size: 64,
+        ..ProcessorConfig::default()
+    };
+    let processor = RaptorQProcessor::new(config);
+
+    let (temp_dir, input_file, symbols_dir) = setup_test_env(SIZE_1MB);
+    let output_file = temp_dir.path().join("decoded_file.dat");
+    let layout_file_path = encode_file_for_decoding(&processor, &input_file, &symbols_dir);
+
+    group.bench_function("decode_many_symbols_1mb", |b| {
+        b.iter(|| {
+            processor
+                .decode_symbols(
+                    symbols_dir.to_str().unwrap(),
+                    output_file.to_str().unwrap(),
+                    layout_file_path.as_str(),
+                )
+                .expect("Failed to decode symbols");
+        });
+    });
+}
+
 // Group encoding benchmarks
 fn encoding_benchmarks(c: &mut Criterion) {
     // Create a benchmark group with specific configuration for encoding
@@ -752,6 +810,32 @@ fn metadata_benchmarks(c: &mut Criterion) {
     group.finish();
 }
 
-// criterion_group!(benches, encoding_benchmarks, decoding_benchmarks, metadata_benchmarks);
-criterion_group!(benches, encoding_benchmarks);
+// Group symbol storage benchmarks
+fn storage_benchmarks(c: &mut Criterion) {
+    // Create a benchmark group comparing the symbol storage modes
+    let mut group = c.benchmark_group("Symbol Storage");
+
+    group.measurement_time(Duration::from_secs(60));
+    group.sample_size(10);
+    bench_symbol_storage_10mb(&mut group);
+    println!();
+
+    group.finish();
+}
+
+// Group symbol lookup benchmarks
+fn symbol_lookup_benchmarks(c: &mut Criterion) {
+    // Create a benchmark group for decodes dominated by opening symbol files
+    let mut group = c.benchmark_group("Symbol Lookup");
+
+    // group.measurement_time(Duration::from_secs(5));  <-- this is default
+    // group.sample_size(100);                          <-- this is default
+    bench_decode_many_symbols_1mb(&mut group);
+    println!();
+
+    group.finish();
+}
+
+// criterion_group!(benches, encoding_benchmarks, decoding_benchmarks, metadata_benchmarks, storage_benchmarks, symbol_lookup_benchmarks);
+criterion_group!(benches, encoding_benchmarks, storage_benchmarks, symbol_lookup_benchmarks);
 criterion_main!(benches);
\ No newline at end of file
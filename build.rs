@@ -22,6 +22,21 @@ fn main() {
 
     println!("cargo:rerun-if-changed=src/lib.rs");
     println!("cargo:rerun-if-changed=src/processor.rs");
+
+    // The OTI and symbol format come from the raptorq crate, so record which version is compiled in
+    println!("cargo:rustc-env=RQ_RAPTORQ_VERSION={}", locked_version("raptorq").unwrap_or_else(|| "unknown".to_string()));
+    println!("cargo:rerun-if-changed=Cargo.lock");
+}
+
+// Version of a dependency as pinned in Cargo.lock, if the lock file is next to the manifest
+fn locked_version(package: &str) -> Option<String> {
+    let lock_file = PathBuf::from(env::var("CARGO_MANIFEST_DIR").ok()?).join("Cargo.lock");
+    let lock = std::fs::read_to_string(lock_file).ok()?;
+    let name_line = format!("name = \"{}\"", package);
+    let mut lines = lock.lines();
+    lines.find(|line| line.trim() == name_line)?;
+    let version_line = lines.next()?.trim();
+    version_line.strip_prefix("version = \"")?.strip_suffix('"').map(str::to_string)
 }
 
 fn target_dir() -> PathBuf {
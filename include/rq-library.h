@@ -15,13 +15,55 @@
 namespace RQLibrary {
 #endif  // __cplusplus
 
+/**
+ * Size of the nul-terminated hash buffer in `BlockInfoC`
+ */
+#define BLOCK_HASH_BUFFER_LEN 64
+
+/**
+ * Layout format written by this version. Layouts from before the field read as 0;
+ * both decode the same way. Bump when a change makes older decoders misread layouts.
+ */
+#define LAYOUT_FORMAT_VERSION 1
+
+/**
+ * Bytes a serialized packet carries in front of its symbol data (the `PayloadId`:
+ * source block number and encoding symbol ID).
+ */
+#define PACKET_HEADER_SIZE_B 4
+
+/**
+ * Metadata of one block of a layout, for C hosts that don't parse JSON
+ */
+typedef struct BlockInfoC {
+  uint64_t block_id;
+  uint64_t original_offset;
+  uint64_t size;
+  uint64_t symbols_count;
+  /**
+   * Base58 hash of the block data, nul-terminated
+   */
+  char hash[BLOCK_HASH_BUFFER_LEN];
+} BlockInfoC;
+
 #ifdef __cplusplus
 extern "C" {
 #endif // __cplusplus
 
+/**
+ * Caps the number of sessions that can be open at once
+ *
+ * Once `max_sessions` sessions are open, raptorq_init_session returns 0 until one
+ * is freed with raptorq_free_session. 0 removes the cap, which is the default.
+ * Sessions already open are kept when the cap is lowered below their number.
+ */
+void raptorq_set_max_sessions(uintptr_t max_sessions);
+
 /**
  * Initializes a RaptorQ session with the given configuration
- * Returns a session ID on success, or 0 on failure
+ * Returns a session ID on success, or 0 on failure (e.g. an invalid symbol size:
+ * it must be 1 to 63, a multiple of 8 from 64 to 65528, or the 65535 default, or
+ * the cap set with raptorq_set_max_sessions is reached)
  */
 uintptr_t raptorq_init_session(uint16_t symbol_size,
                                uint8_t redundancy_factor,
@@ -33,6 +75,82 @@ uintptr_t raptorq_init_session(uint16_t symbol_size,
  */
 bool raptorq_free_session(uintptr_t session_id);
 
+/**
+ * Changes the configuration of an open session, keeping its session ID
+ *
+ * Takes the same settings as raptorq_init_session; the session's other settings
+ * are kept. Waits for the calls running on the session to return first; calls
+ * made on the session meanwhile wait for the reconfiguration to finish, and then
+ * run with the new settings.
+ *
+ * Returns true on success, false on invalid session or an invalid configuration
+ * (the session keeps its previous configuration). Use
+ * raptorq_reconfigure_session_timeout to bound the wait.
+ */
+bool raptorq_reconfigure_session(uintptr_t session_id,
+                                 uint16_t symbol_size,
+                                 uint8_t redundancy_factor,
+                                 uint64_t max_memory_mb,
+                                 uint64_t concurrency_limit);
+
+/**
+ * Changes the configuration of an open session, keeping its session ID
+ *
+ * As raptorq_reconfigure_session, but waits at most `timeout_ms` for the calls
+ * running on the session to return.
+ *
+ * Returns:
+ * *   0 on success
+ * *  -2 on an invalid configuration (the session keeps its previous configuration)
+ * *  -5 on invalid session
+ * * -18 on timeout (calls still running, or another reconfiguration pending)
+ */
+int32_t raptorq_reconfigure_session_timeout(uintptr_t session_id,
+                                            uint16_t symbol_size,
+                                            uint8_t redundancy_factor,
+                                            uint64_t max_memory_mb,
+                                            uint64_t concurrency_limit,
+                                            uint64_t timeout_ms);
+
+/**
+ * Reads back the configuration of an open session
+ *
+ * Arguments:
+ * * `session_id` - Session ID returned from raptorq_init_session
+ * * `out_symbol_size` - Receives the symbol size in bytes
+ * * `out_redundancy_factor` - Receives the redundancy factor
+ * * `out_max_memory_mb` - Receives the memory limit in MB
+ * * `out_concurrency_limit` - Receives the concurrency limit
+ *
+ * Returns:
+ * *  0 on success
+ * * -2 on invalid parameters (a null pointer)
+ * * -5 on invalid session
+ *
+ * # Safety
+ *
+ * The caller must pass each `out_*` pointer pointing to a writable value of its type.
+ */
+int32_t raptorq_get_session_config(uintptr_t session_id,
+                                   uint16_t *out_symbol_size,
+                                   uint8_t *out_redundancy_factor,
+                                   uint64_t *out_max_memory_mb,
+                                   uint64_t *out_concurrency_limit);
+
+/**
+ * Stops a session from accepting new tasks and waits for its active tasks to finish
+ *
+ * Arguments:
+ * * `session_id` - Session ID returned from raptorq_init_session
+ * * `timeout_ms` - Maximum time to wait for active tasks, in milliseconds
+ *
+ * Returns:
+ * *   0 on success
+ * *  -5 on invalid session
+ * * -18 on timeout (tasks still running)
+ */
+int32_t raptorq_shutdown_session(uintptr_t session_id, uint64_t timeout_ms);
+
 /**
  * Encodes a file using RaptorQ - streaming implementation
  *
@@ -99,6 +217,45 @@ int32_t raptorq_encode_file(uintptr_t session_id,
                             char *result_buffer,
                             uintptr_t result_buffer_len);
 
+/**
+ * Encodes an in-memory buffer using RaptorQ
+ *
+ * Arguments:
+ * * `session_id` - Session ID returned from raptorq_init_session
+ * * `data` - Pointer to the bytes to encode
+ * * `data_len` - Number of bytes at `data`
+ * * `output_dir` - Directory where symbols will be written
+ * * `block_size` - Size of blocks to process at once (0 = auto)
+ * * `result_buffer` - Buffer to store the result (JSON metadata)
+ * * `result_buffer_len` - Length of the result buffer
+ *
+ * Returns:
+ * *   0 on success
+ * *  -1 on generic error
+ * *  -2 on invalid parameters
+ * *  -3 on invalid response
+ * *  -4 on bad return buffer size
+ * *  -5 on invalid session
+ * * -11 on IO error
+ * * -13 on Invalid Path
+ * * -14 on Encoding failed
+ * * -16 on Memory limit exceeded
+ * * -17 on Concurrency limit reached
+ *
+ * # Safety
+ *
+ * The caller must pass `data` pointing to at least `data_len` readable bytes,
+ * `output_dir` as a valid nul-terminated string and `result_buffer` pointing to at
+ * least `result_buffer_len` writable bytes.
+ */
+int32_t raptorq_encode_buffer(uintptr_t session_id,
+                              const uint8_t *data,
+                              uintptr_t data_len,
+                              const char *output_dir,
+                              uintptr_t block_size,
+                              char *result_buffer,
+                              uintptr_t result_buffer_len);
+
 /**
  * Gets the last error message from the processor
  *
@@ -144,6 +301,59 @@ int32_t raptorq_decode_symbols(uintptr_t session_id,
                                const char *output_path,
                                const char *layout_path);
 
+/**
+ * Gets the number of blocks in a layout file
+ *
+ * Arguments:
+ * * `layout_path` - Path to the layout file
+ *
+ * Returns:
+ * * The number of blocks on success
+ * *  -2 on invalid parameters
+ * * -11 on IO error
+ * * -12 on File not found
+ * * -15 on invalid layout
+ */
+int64_t raptorq_layout_block_count(const char *layout_path);
+
+/**
+ * Gets the metadata of one block of a layout file
+ *
+ * Arguments:
+ * * `layout_path` - Path to the layout file
+ * * `block_id` - ID of the block to describe
+ * * `out` - Struct to fill with the block's metadata
+ *
+ * Returns:
+ * *   0 on success
+ * *  -2 on invalid parameters (including a block ID not in the layout)
+ * * -11 on IO error
+ * * -12 on File not found
+ * * -15 on invalid layout
+ *
+ * # Safety
+ *
+ * The caller must pass `layout_path` as a valid nul-terminated string and `out`
+ * pointing to a writable `BlockInfoC`.
+ */
+int32_t raptorq_layout_block_info(const char *layout_path,
+                                  uint64_t block_id,
+                                  struct BlockInfoC *out);
+
+/**
+ * Gets the size of the file a layout decodes to, so hosts can check the space
+ * available before decoding
+ *
+ * Arguments:
+ * * `session_id` - Session ID returned from raptorq_init_session
+ * * `layout_path` - Path to the layout file
+ *
+ * Returns:
+ * * The decoded file size in bytes on success
+ * * -1 on error; for a valid session, the reason is kept as its last error
+ */
+int64_t raptorq_decoded_size(uintptr_t session_id, const char *layout_path);
+
 /**
  * Gets a recommended block size based on file size and available memory
  *
@@ -157,11 +367,70 @@ int32_t raptorq_decode_symbols(uintptr_t session_id,
  */
 uintptr_t raptorq_get_recommended_block_size(uintptr_t session_id, uint64_t file_size);
 
+/**
+ * Checks that a symbol's bytes hash to the ID it is stored under
+ *
+ * IDs are checked as BLAKE3 hashes, the default. Symbols of a layout whose
+ * `hash_algorithm` is another hash must go through raptorq_verify_symbol_with_hash,
+ * as this function reports them all as mismatches.
+ *
+ * Arguments:
+ * * `data` - Pointer to the serialized symbol
+ * * `data_len` - Number of bytes at `data`
+ * * `expected_id` - The symbol ID the bytes were stored under
+ *
+ * Returns:
+ * *  1 if the symbol matches its ID
+ * *  0 if it does not
+ * * -2 on invalid parameters
+ *
+ * # Safety
+ *
+ * The caller must pass `data` pointing to at least `data_len` readable bytes and
+ * `expected_id` as a valid nul-terminated string.
+ */
+int32_t raptorq_verify_symbol(const uint8_t *data, uintptr_t data_len, const char *expected_id);
+
+/**
+ * Checks that a symbol's bytes hash to the ID it is stored under, with the given hash
+ *
+ * Arguments:
+ * * `data` - Pointer to the serialized symbol
+ * * `data_len` - Number of bytes at `data`
+ * * `expected_id` - The symbol ID the bytes were stored under
+ * * `hash_algorithm` - The layout's `hash_algorithm`: "blake3" or "sha256"
+ *
+ * Returns:
+ * *  1 if the symbol matches its ID
+ * *  0 if it does not
+ * * -2 on invalid parameters, including an unknown hash
+ *
+ * # Safety
+ *
+ * The caller must pass `data` pointing to at least `data_len` readable bytes, and
+ * `expected_id` and `hash_algorithm` as valid nul-terminated strings.
+ */
+int32_t raptorq_verify_symbol_with_hash(const uint8_t *data,
+                                        uintptr_t data_len,
+                                        const char *expected_id,
+                                        const char *hash_algorithm);
+
+/**
+ * Returns the number of bytes a serialized packet (symbol file) adds on top of
+ * the symbol data
+ */
+uintptr_t raptorq_packet_header_size(void);
+
 /**
  * Version information
  */
 int32_t raptorq_version(char *version_buffer, uintptr_t version_buffer_len);
 
+/**
+ * Version of the `raptorq` crate compiled in, which defines the OTI and symbol format
+ */
+int32_t raptorq_engine_version(char *version_buffer, uintptr_t version_buffer_len);
+
 #ifdef __cplusplus
 }  // extern "C"
 #endif  // __cplusplus
@@ -245,6 +245,67 @@ window.syncDirExists = function(path) {
     return dirs.includes(path);
 };
 
+/**
+ * Synchronous listing of the files directly inside a directory (called from Rust)
+ * @param {string} path - Directory path
+ * @returns {string[]} - File names (not full paths)
+ */
+window.syncListFiles = function(path) {
+    console.log(`[JS] syncListFiles: ${path}`);
+    const prefix = path.endsWith('/') ? path : `${path}/`;
+    const names = [];
+    for (let i = 0; i < localStorage.length; i++) {
+        const key = localStorage.key(i);
+        if (key.startsWith(`file_data:${prefix}`)) {
+            const name = key.slice(`file_data:${prefix}`.length);
+            if (name.length > 0 && !name.includes('/')) {
+                names.push(name);
+            }
+        }
+    }
+    return names;
+};
+
+/**
+ * Synchronous count of the files directly inside a directory (called from Rust)
+ * @param {string} path - Directory path
+ * @returns {number}
+ */
+window.syncCountFiles = function(path) {
+    console.log(`[JS] syncCountFiles: ${path}`);
+    return window.syncListFiles(path).length;
+};
+
+/**
+ * Synchronous removal of a file (called from Rust)
+ * @param {string} path - File path
+ */
+window.syncRemoveFile = function(path) {
+    console.log(`[JS] syncRemoveFile: ${path}`);
+    if (localStorage.getItem(`file_data:${path}`) === null) {
+        throw new Error(`File not found: ${path}`);
+    }
+    localStorage.removeItem(`file_data:${path}`);
+    localStorage.removeItem(`file_metadata:${path}`);
+};
+
+/**
+ * Synchronous rename of a file, replacing the target (called from Rust)
+ * @param {string} from - Current file path
+ * @param {string} to - New file path
+ */
+window.syncRenameFile = function(from, to) {
+    console.log(`[JS] syncRenameFile: ${from} -> ${to}`);
+    const data = localStorage.getItem(`file_data:${from}`);
+    if (data === null) {
+        throw new Error(`File not found: ${from}`);
+    }
+    localStorage.setItem(`file_data:${to}`, data);
+    localStorage.setItem(`file_metadata:${to}`, localStorage.getItem(`file_metadata:${from}`) || '{"size": 0}');
+    localStorage.removeItem(`file_data:${from}`);
+    localStorage.removeItem(`file_metadata:${from}`);
+};
+
 /**
  * Check if a directory exists (async version)
  * @param {string} path - Directory path to check
@@ -137,6 +137,58 @@ window.syncDirExists = function(path) {
     return memDirs.has(path);
 };
 
+/**
+ * Synchronous listing of the files directly inside a directory (called from Rust)
+ * @param {string} path
+ * @returns {string[]} - File names (not full paths)
+ */
+window.syncListFiles = function(path) {
+    const prefix = path.endsWith('/') ? path : `${path}/`;
+    const names = [];
+    for (const filePath of memFiles.keys()) {
+        if (filePath.startsWith(prefix)) {
+            const name = filePath.slice(prefix.length);
+            if (name.length > 0 && !name.includes('/')) {
+                names.push(name);
+            }
+        }
+    }
+    return names;
+};
+
+/**
+ * Synchronous count of the files directly inside a directory (called from Rust)
+ * @param {string} path
+ * @returns {number}
+ */
+window.syncCountFiles = function(path) {
+    return window.syncListFiles(path).length;
+};
+
+/**
+ * Synchronous removal of a file (called from Rust)
+ * @param {string} path
+ */
+window.syncRemoveFile = function(path) {
+    if (!memFiles.delete(path)) {
+        throw new Error(`File not found: ${path}`);
+    }
+};
+
+/**
+ * Synchronous rename of a file, replacing the target (called from Rust)
+ * @param {string} from
+ * @param {string} to
+ */
+window.syncRenameFile = function(from, to) {
+    const data = memFiles.get(from);
+    if (data === undefined) {
+        throw new Error(`File not found: ${from}`);
+    }
+    memFiles.set(to, data);
+    memFiles.delete(from);
+};
+
 /**
  * Async check if a directory exists
  * @param {string} path
@@ -144,4 +196,4 @@ window.syncDirExists = function(path) {
  */
 export async function dirExists(path) {
     return memDirs.has(path);
-}
\ No newline at end of file
+}
@@ -3,9 +3,13 @@
 //! This module defines the core traits for platform-abstracted file and directory I/O:
 //! - `FileReader`: For efficient, chunked file reading
 //! - `FileWriter`: For efficient, chunked file writing
-//! - `DirManager`: For directory creation
+//! - `DirManager`: For directory creation and listing, and file removal and renaming
+//! - `StorageBackend`: A `DirManager` bundled with reader and writer factories
+//! - `AsyncFileReader` / `AsyncFileWriter`: Async reading and writing, for the browser
 //!
-//! Implementations are provided in platform-specific modules.
+//! Implementations are provided in platform-specific modules, in `mem` for
+//! an in-memory filesystem, and in `s3` (behind the `s3` feature) for
+//! S3-compatible object storage.
 #[cfg(all(not(target_arch = "wasm32"), not(feature = "browser-wasm")))]
 pub mod native;
 #[cfg(all(not(target_arch = "wasm32"), not(feature = "browser-wasm")))]
@@ -16,6 +20,10 @@ pub mod wasm;
 #[cfg(all(target_arch = "wasm32", feature = "browser-wasm"))]
 pub use wasm::*;
 
+pub mod mem;
+
+#[cfg(feature = "s3")]
+pub mod s3;
 
 /// Trait for platform-abstracted, memory-efficient file reading.
 pub trait FileReader {
@@ -27,6 +35,51 @@ pub trait FileReader {
     fn read_chunk(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, String>;
 }
 
+/// Reads from `offset` until `buf` is full or EOF is reached, looping over short reads.
+/// Returns the number of bytes read.
+///
+/// Fails instead of looping forever or overrunning `buf` when the reader reports
+/// more bytes than were asked for, which a misbehaving `FileReader` can do.
+pub fn read_full_chunk(reader: &mut dyn FileReader, offset: u64, buf: &mut [u8]) -> Result<usize, String> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let remaining = buf.len() - filled;
+        let bytes_read = reader.read_chunk(offset + filled as u64, &mut buf[filled..])?;
+        if bytes_read == 0 {
+            break;
+        }
+        if bytes_read > remaining {
+            return Err(format!(
+                "Reader reported {} bytes read at offset {} but only {} were requested",
+                bytes_read,
+                offset + filled as u64,
+                remaining
+            ));
+        }
+        filled += bytes_read;
+    }
+    Ok(filled)
+}
+
+/// Like `read_full_chunk`, but asks the reader for at most `chunk_size` bytes per call.
+/// A `chunk_size` of 0 reads into the whole of `buf` at once.
+pub fn read_chunked(reader: &mut dyn FileReader, offset: u64, buf: &mut [u8], chunk_size: usize) -> Result<usize, String> {
+    if chunk_size == 0 {
+        return read_full_chunk(reader, offset, buf);
+    }
+
+    let mut filled = 0;
+    for chunk in buf.chunks_mut(chunk_size) {
+        let chunk_len = chunk.len();
+        let bytes_read = read_full_chunk(reader, offset + filled as u64, chunk)?;
+        filled += bytes_read;
+        if bytes_read < chunk_len {
+            break;
+        }
+    }
+    Ok(filled)
+}
+
 /// Trait for platform-abstracted, memory-efficient file writing.
 pub trait FileWriter {
     /// Writes a chunk of bytes to the file at the given offset.
@@ -34,6 +87,88 @@ pub trait FileWriter {
 
     /// Flushes any buffered data to the file (optional for buffered writers).
     fn flush(&mut self) -> Result<(), String>;
+
+    /// Writes `data` as the whole content of a freshly created file and flushes it.
+    /// Nothing is synced to disk.
+    ///
+    /// Each symbol file is written with one call, so writers override this to skip
+    /// the per-file seek and flush when their flush does nothing for a whole write.
+    fn write_all_and_sync(&mut self, data: &[u8]) -> Result<(), String> {
+        self.write_chunk(0, data)?;
+        self.flush()
+    }
+}
+
+/// Async counterpart of `FileReader`, for hosts whose file I/O only completes
+/// asynchronously, such as the browser.
+///
+/// The futures aren't required to be `Send`: they are driven on the host's own
+/// thread, e.g. through `future_to_promise` in the browser.
+#[allow(async_fn_in_trait)]
+pub trait AsyncFileReader {
+    /// Returns the total size of the file in bytes.
+    fn file_size(&self) -> Result<u64, String>;
+
+    /// Reads a chunk of bytes from the file at the given offset.
+    /// Returns the number of bytes read. If 0, EOF has been reached.
+    async fn read_chunk(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, String>;
+}
+
+/// Async counterpart of `FileWriter`.
+#[allow(async_fn_in_trait)]
+pub trait AsyncFileWriter {
+    /// Writes a chunk of bytes to the file at the given offset.
+    async fn write_chunk(&mut self, offset: usize, data: &[u8]) -> Result<(), String>;
+
+    /// Flushes any buffered data to the file.
+    async fn flush(&mut self) -> Result<(), String>;
+}
+
+/// Async `read_full_chunk`: reads from `offset` until `buf` is full or EOF is reached.
+pub async fn read_full_chunk_async<R: AsyncFileReader + ?Sized>(reader: &mut R, offset: u64, buf: &mut [u8]) -> Result<usize, String> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let remaining = buf.len() - filled;
+        let bytes_read = reader.read_chunk(offset + filled as u64, &mut buf[filled..]).await?;
+        if bytes_read == 0 {
+            break;
+        }
+        if bytes_read > remaining {
+            return Err(format!(
+                "Reader reported {} bytes read at offset {} but only {} were requested",
+                bytes_read,
+                offset + filled as u64,
+                remaining
+            ));
+        }
+        filled += bytes_read;
+    }
+    Ok(filled)
+}
+
+/// Wraps a synchronous reader or writer, as opened by `open_file_reader` and
+/// friends, to serve the async traits, each call completing immediately. Lets
+/// the async code paths run over native or in-memory files.
+pub struct BlockingIo<T: ?Sized>(pub Box<T>);
+
+impl<T: FileReader + ?Sized> AsyncFileReader for BlockingIo<T> {
+    fn file_size(&self) -> Result<u64, String> {
+        self.0.file_size()
+    }
+
+    async fn read_chunk(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, String> {
+        self.0.read_chunk(offset, buf)
+    }
+}
+
+impl<T: FileWriter + ?Sized> AsyncFileWriter for BlockingIo<T> {
+    async fn write_chunk(&mut self, offset: usize, data: &[u8]) -> Result<(), String> {
+        self.0.write_chunk(offset, data)
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        self.0.flush()
+    }
 }
 
 /// Trait for platform-abstracted directory creation.
@@ -46,6 +181,60 @@ pub trait DirManager {
 
     /// Returns the number of files in the given directory.
     fn count_files(&self, path: &str) -> Result<usize, String>;
+
+    /// Returns the names (not full paths) of the files in the given directory.
+    /// Not supported unless implemented.
+    fn list_files(&self, path: &str) -> Result<Vec<String>, String> {
+        Err(format!("Listing files is not supported: {}", path))
+    }
+
+    /// Deletes the file at the given path. Not supported unless implemented.
+    fn remove_file(&self, path: &str) -> Result<(), String> {
+        Err(format!("Removing files is not supported: {}", path))
+    }
+
+    /// Renames the file at `from` to `to`, replacing `to` if it exists.
+    /// Not supported unless implemented.
+    fn rename_file(&self, from: &str, to: &str) -> Result<(), String> {
+        Err(format!("Renaming files is not supported: {} -> {}", from, to))
+    }
+}
+
+/// Trait for a complete place to store files: where encoding writes its symbols
+/// and layout, and where they can be read back from.
+pub trait StorageBackend: Sync {
+    /// Returns the directory manager of this storage.
+    fn dir_manager(&self) -> &dyn DirManager;
+
+    /// Opens a reader for the file at the given path.
+    fn open_file_reader(&self, path: &str) -> Result<Box<dyn FileReader>, String>;
+
+    /// Creates (or truncates) the file at the given path and opens a writer for it.
+    fn open_file_writer(&self, path: &str) -> Result<Box<dyn FileWriter>, String>;
+}
+
+/// StorageBackend over the platform filesystem, the one `open_file_reader`,
+/// `open_file_writer` and `get_dir_manager` use.
+pub struct PlatformStorage;
+
+impl StorageBackend for PlatformStorage {
+    #[cfg(all(not(target_arch = "wasm32"), not(feature = "browser-wasm")))]
+    fn dir_manager(&self) -> &dyn DirManager {
+        &native::NativeDirManager
+    }
+
+    #[cfg(all(target_arch = "wasm32", feature = "browser-wasm"))]
+    fn dir_manager(&self) -> &dyn DirManager {
+        &wasm::BrowserDirManager
+    }
+
+    fn open_file_reader(&self, path: &str) -> Result<Box<dyn FileReader>, String> {
+        open_file_reader(path)
+    }
+
+    fn open_file_writer(&self, path: &str) -> Result<Box<dyn FileWriter>, String> {
+        open_file_writer(path)
+    }
 }
 
 /// Opens a platform-appropriate file reader.
@@ -57,6 +246,15 @@ pub fn open_file_reader(path: &str) -> Result<Box<dyn FileReader>, String> {
     Ok(Box::new(native::NativeFileReader::open(path)?))
 }
 
+/// Opens a native file reader backed by a memory map of the file.
+///
+/// Suited to many random-access reads over a large file; `open_file_reader`
+/// remains the default.
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "browser-wasm")))]
+pub fn open_file_reader_mmap(path: &str) -> Result<Box<dyn FileReader>, String> {
+    Ok(Box::new(native::MmapFileReader::open(path)?))
+}
+
 /// Opens a platform-appropriate file reader.
 /// 
 /// On native platforms, uses std::fs::File.
@@ -102,6 +300,21 @@ pub fn get_dir_manager() -> Box<dyn DirManager> {
     Box::new(wasm::BrowserDirManager)
 }
 
+/// Opens a reader of a file in an in-memory filesystem.
+pub fn open_file_reader_in(files: &mem::MemFiles, path: &str) -> Result<Box<dyn FileReader>, String> {
+    Ok(Box::new(mem::MemFileReader::open(files.clone(), path)?))
+}
+
+/// Opens a writer of a file in an in-memory filesystem, truncating the file.
+pub fn open_file_writer_in(files: &mem::MemFiles, path: &str) -> Result<Box<dyn FileWriter>, String> {
+    Ok(Box::new(mem::MemFileWriter::create(files.clone(), path)?))
+}
+
+/// Creates a directory manager over an in-memory filesystem.
+pub fn get_dir_manager_in(files: &mem::MemFiles) -> Box<dyn DirManager> {
+    Box::new(mem::MemDirManager::new(files.clone()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -160,6 +373,183 @@ mod tests {
         remove_file(&path).unwrap();
     }
 
+    #[test]
+    fn test_mmap_reader_matches_seek_reader() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let path = write_test_file(&data);
+        let mut mmap = open_file_reader_mmap(&path).unwrap();
+        let mut seek = open_file_reader(&path).unwrap();
+        assert_eq!(mmap.file_size().unwrap(), data.len() as u64);
+        let mut a = [0u8; 777];
+        let mut b = [0u8; 777];
+        for offset in [0u64, 1, 4096, 9_500, 9_999] {
+            let na = mmap.read_chunk(offset, &mut a).unwrap();
+            let nb = seek.read_chunk(offset, &mut b).unwrap();
+            assert_eq!(na, nb);
+            assert_eq!(&a[..na], &b[..nb]);
+        }
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_reader_eof() {
+        let path = write_test_file(b"short");
+        let mut reader = open_file_reader_mmap(&path).unwrap();
+        let mut buf = [0u8; 10];
+        assert_eq!(reader.read_chunk(5, &mut buf).unwrap(), 0);
+        assert_eq!(reader.read_chunk(100, &mut buf).unwrap(), 0);
+        remove_file(&path).unwrap();
+
+        let path = write_test_file(b"");
+        let mut reader = open_file_reader_mmap(&path).unwrap();
+        assert_eq!(reader.file_size().unwrap(), 0);
+        assert_eq!(reader.read_chunk(0, &mut buf).unwrap(), 0);
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_all_and_sync_replaces_contents() {
+        let path = write_test_file(b"previous longer contents");
+        let mut writer = open_file_writer(&path).unwrap();
+        writer.write_all_and_sync(b"symbol").unwrap();
+        drop(writer);
+
+        let mut reader = open_file_reader(&path).unwrap();
+        assert_eq!(reader.file_size().unwrap(), 6);
+        let mut buf = [0u8; 6];
+        assert_eq!(reader.read_chunk(0, &mut buf).unwrap(), 6);
+        assert_eq!(&buf, b"symbol");
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_in_memory_filesystem() {
+        let files = mem::MemFiles::default();
+
+        let mut writer = open_file_writer_in(&files, "dir/a.bin").unwrap();
+        writer.write_chunk(0, b"hello").unwrap();
+        writer.write_chunk(5, b" world").unwrap();
+        writer.flush().unwrap();
+        open_file_writer_in(&files, "dir/b.bin").unwrap().write_all_and_sync(b"b").unwrap();
+
+        let mut reader = open_file_reader_in(&files, "dir/a.bin").unwrap();
+        assert_eq!(reader.file_size().unwrap(), 11);
+        let mut buf = [0u8; 16];
+        assert_eq!(reader.read_chunk(6, &mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"world");
+        assert_eq!(reader.read_chunk(11, &mut buf).unwrap(), 0);
+        assert!(open_file_reader_in(&files, "dir/missing.bin").is_err());
+
+        let dir_manager = get_dir_manager_in(&files);
+        assert!(dir_manager.dir_exists("dir").unwrap());
+        assert!(!dir_manager.dir_exists("other").unwrap());
+        assert_eq!(dir_manager.count_files("dir").unwrap(), 2);
+        dir_manager.remove_file("dir/b.bin").unwrap();
+        assert_eq!(dir_manager.list_files("dir").unwrap(), vec!["a.bin".to_string()]);
+    }
+
+    #[cfg(feature = "s3")]
+    #[derive(Default)]
+    struct FakeObjectClient {
+        objects: std::sync::Mutex<std::collections::BTreeMap<(String, String), Vec<u8>>>,
+        puts: std::sync::atomic::AtomicUsize,
+    }
+
+    #[cfg(feature = "s3")]
+    impl s3::ObjectClient for FakeObjectClient {
+        fn head_object(&self, bucket: &str, key: &str) -> Result<Option<u64>, String> {
+            let objects = self.objects.lock().unwrap();
+            Ok(objects.get(&(bucket.to_string(), key.to_string())).map(|data| data.len() as u64))
+        }
+
+        fn get_object_range(&self, bucket: &str, key: &str, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+            let objects = self.objects.lock().unwrap();
+            let data = objects.get(&(bucket.to_string(), key.to_string())).ok_or("NoSuchKey")?;
+            let start = std::cmp::min(offset as usize, data.len());
+            let end = std::cmp::min(start + len, data.len());
+            Ok(data[start..end].to_vec())
+        }
+
+        fn put_object(&self, bucket: &str, key: &str, data: Vec<u8>) -> Result<(), String> {
+            self.puts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            self.objects.lock().unwrap().insert((bucket.to_string(), key.to_string()), data);
+            Ok(())
+        }
+
+        fn list_keys(&self, bucket: &str, prefix: &str) -> Result<Vec<String>, String> {
+            let objects = self.objects.lock().unwrap();
+            Ok(objects.keys()
+                .filter(|(b, k)| b == bucket && k.starts_with(prefix))
+                .map(|(_, k)| k.clone())
+                .collect())
+        }
+
+        fn delete_object(&self, bucket: &str, key: &str) -> Result<(), String> {
+            self.objects.lock().unwrap().remove(&(bucket.to_string(), key.to_string()));
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "s3")]
+    #[test]
+    fn test_s3_storage() {
+        let client = std::sync::Arc::new(FakeObjectClient::default());
+        let storage = s3::S3Storage::new(client.clone()).unwrap();
+
+        let mut writer = storage.open_file_writer("bucket/dir/a.bin").unwrap();
+        writer.write_chunk(0, b"hello").unwrap();
+        writer.write_chunk(5, b" world").unwrap();
+        assert!(writer.write_chunk(0, b"again").is_err());
+        assert!(writer.write_chunk(20, b"gap").is_err());
+        writer.flush().unwrap();
+        // Nothing new to upload
+        writer.flush().unwrap();
+        assert_eq!(client.puts.load(std::sync::atomic::Ordering::SeqCst), 1);
+        storage.open_file_writer("bucket/dir/b.bin").unwrap().write_all_and_sync(b"b").unwrap();
+        storage.open_file_writer("bucket/dir/sub/c.bin").unwrap().write_all_and_sync(b"c").unwrap();
+        assert!(storage.open_file_writer("bucket").is_err());
+
+        let mut reader = storage.open_file_reader("bucket/dir/a.bin").unwrap();
+        assert_eq!(reader.file_size().unwrap(), 11);
+        let mut buf = [0u8; 16];
+        assert_eq!(reader.read_chunk(6, &mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"world");
+        assert_eq!(reader.read_chunk(11, &mut buf).unwrap(), 0);
+        assert!(storage.open_file_reader("bucket/dir/missing.bin").is_err());
+
+        let dir_manager = storage.dir_manager();
+        assert!(dir_manager.dir_exists("bucket/dir").unwrap());
+        assert!(dir_manager.dir_exists("bucket/dir/sub").unwrap());
+        assert!(!dir_manager.dir_exists("bucket/other").unwrap());
+        assert_eq!(dir_manager.count_files("bucket/dir").unwrap(), 2);
+        dir_manager.rename_file("bucket/dir/b.bin", "bucket/dir/d.bin").unwrap();
+        dir_manager.remove_file("bucket/dir/a.bin").unwrap();
+        assert!(dir_manager.remove_file("bucket/dir/a.bin").is_err());
+        assert_eq!(dir_manager.list_files("bucket/dir").unwrap(), vec!["d.bin".to_string()]);
+    }
+
+    #[cfg(feature = "s3")]
+    #[test]
+    fn test_s3_sdk_client_errors() {
+        use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region, retry::RetryConfig};
+
+        // Nothing listens on the endpoint, so every request fails without retrying
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("key", "secret", None, None, "test"))
+            .endpoint_url("http://127.0.0.1:1")
+            .force_path_style(true)
+            .retry_config(RetryConfig::disabled())
+            .build();
+        let storage = s3::S3Storage::new(std::sync::Arc::new(aws_sdk_s3::Client::from_conf(config))).unwrap();
+
+        let err = storage.open_file_reader("bucket/dir/a.bin").err().unwrap();
+        assert!(err.contains("bucket/dir/a.bin"), "Unexpected error: {}", err);
+        assert!(storage.open_file_writer("bucket/dir/a.bin").unwrap().write_all_and_sync(b"a").is_err());
+        assert!(storage.dir_manager().dir_exists("bucket/dir").is_err());
+    }
+
     #[test]
     fn test_trait_object_usage() {
         let data = b"trait object test";
@@ -172,4 +562,67 @@ mod tests {
         assert_eq!(reader.file_size().unwrap(), data.len() as u64);
         remove_file(&path).unwrap();
     }
+
+    // Serves at most `max_read` bytes per call from `data`
+    struct TrickleReader {
+        data: Vec<u8>,
+        max_read: usize,
+    }
+
+    impl FileReader for TrickleReader {
+        fn file_size(&self) -> Result<u64, String> {
+            Ok(self.data.len() as u64)
+        }
+
+        fn read_chunk(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, String> {
+            let start = (offset as usize).min(self.data.len());
+            let n = buf.len().min(self.max_read).min(self.data.len() - start);
+            buf[..n].copy_from_slice(&self.data[start..start + n]);
+            Ok(n)
+        }
+    }
+
+    // Claims to fill more than the buffer without ever reaching EOF
+    struct StuckReader;
+
+    impl FileReader for StuckReader {
+        fn file_size(&self) -> Result<u64, String> {
+            Ok(u64::MAX)
+        }
+
+        fn read_chunk(&mut self, _offset: u64, buf: &mut [u8]) -> Result<usize, String> {
+            Ok(buf.len() + 1)
+        }
+    }
+
+    #[test]
+    fn test_read_full_chunk_loops_over_short_reads() {
+        let mut reader = TrickleReader { data: b"abcdefghij".to_vec(), max_read: 3 };
+        let mut buf = [0u8; 8];
+        assert_eq!(read_full_chunk(&mut reader, 1, &mut buf).unwrap(), 8);
+        assert_eq!(&buf, b"bcdefghi");
+
+        // Stops at EOF
+        let mut buf = [0u8; 8];
+        assert_eq!(read_full_chunk(&mut reader, 6, &mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], b"ghij");
+    }
+
+    #[test]
+    fn test_read_chunked_various_chunk_sizes() {
+        let data = b"abcdefghijklmnopqrstuvwxyz";
+        for chunk_size in [0, 1, 3, 7, 26, 100] {
+            let mut reader = TrickleReader { data: data.to_vec(), max_read: 5 };
+            let mut buf = [0u8; 24];
+            assert_eq!(read_chunked(&mut reader, 2, &mut buf, chunk_size).unwrap(), 24, "chunk size {}", chunk_size);
+            assert_eq!(&buf, &data[2..], "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn test_read_full_chunk_rejects_misbehaving_reader() {
+        let mut buf = [0u8; 16];
+        let err = read_full_chunk(&mut StuckReader, 0, &mut buf).unwrap_err();
+        assert!(err.contains("17 bytes read"), "unexpected error: {}", err);
+    }
 }
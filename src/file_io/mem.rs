@@ -0,0 +1,174 @@
+//! In-memory implementations of FileReader, FileWriter, DirManager and StorageBackend.
+//!
+//! Files live in a shared map of path to contents. Directories are implicit: a
+//! directory exists as long as a file is stored under it, as in an object store.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use parking_lot::Mutex;
+
+use super::{DirManager, FileReader, FileWriter, StorageBackend};
+
+/// Shared virtual filesystem: file path to file contents.
+pub type MemFiles = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+// Whether `path` is a file directly inside `dir`
+fn is_in_dir(path: &str, dir: &str) -> bool {
+    Path::new(path).parent() == Some(Path::new(dir))
+}
+
+/// In-memory implementation of FileReader.
+pub struct MemFileReader {
+    files: MemFiles,
+    path: String,
+}
+
+impl MemFileReader {
+    pub fn open(files: MemFiles, path: &str) -> Result<Self, String> {
+        if !files.lock().contains_key(path) {
+            return Err(format!("File not found: {}", path));
+        }
+        Ok(Self { files, path: path.to_string() })
+    }
+}
+
+impl FileReader for MemFileReader {
+    fn file_size(&self) -> Result<u64, String> {
+        self.files.lock().get(&self.path)
+            .map(|data| data.len() as u64)
+            .ok_or_else(|| format!("File not found: {}", self.path))
+    }
+
+    fn read_chunk(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, String> {
+        let files = self.files.lock();
+        let data = files.get(&self.path).ok_or_else(|| format!("File not found: {}", self.path))?;
+        let start = std::cmp::min(offset, data.len() as u64) as usize;
+        let n = std::cmp::min(buf.len(), data.len() - start);
+        buf[..n].copy_from_slice(&data[start..start + n]);
+        Ok(n)
+    }
+}
+
+/// In-memory implementation of FileWriter. Creating one truncates the file.
+pub struct MemFileWriter {
+    files: MemFiles,
+    path: String,
+}
+
+impl MemFileWriter {
+    pub fn create(files: MemFiles, path: &str) -> Result<Self, String> {
+        files.lock().insert(path.to_string(), Vec::new());
+        Ok(Self { files, path: path.to_string() })
+    }
+}
+
+impl FileWriter for MemFileWriter {
+    fn write_chunk(&mut self, offset: usize, data: &[u8]) -> Result<(), String> {
+        let mut files = self.files.lock();
+        let file = files.entry(self.path.clone()).or_default();
+        let end = offset + data.len();
+        if file.len() < end {
+            file.resize(end, 0);
+        }
+        file[offset..end].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    // The contents replace the file's in one go
+    fn write_all_and_sync(&mut self, data: &[u8]) -> Result<(), String> {
+        self.files.lock().insert(self.path.clone(), data.to_vec());
+        Ok(())
+    }
+}
+
+/// In-memory implementation of DirManager.
+pub struct MemDirManager {
+    files: MemFiles,
+}
+
+impl MemDirManager {
+    pub fn new(files: MemFiles) -> Self {
+        Self { files }
+    }
+}
+
+impl DirManager for MemDirManager {
+    fn create_dir_all(&self, _path: &str) -> Result<(), String> {
+        // Directories are implied by the files stored under them
+        Ok(())
+    }
+
+    fn dir_exists(&self, path: &str) -> Result<bool, String> {
+        let dir = Path::new(path);
+        Ok(self.files.lock().keys().any(|name| Path::new(name).starts_with(dir) && Path::new(name) != dir))
+    }
+
+    fn count_files(&self, path: &str) -> Result<usize, String> {
+        Ok(self.files.lock().keys().filter(|name| is_in_dir(name, path)).count())
+    }
+
+    fn list_files(&self, path: &str) -> Result<Vec<String>, String> {
+        Ok(self.files.lock().keys()
+            .filter(|name| is_in_dir(name, path))
+            .filter_map(|name| Path::new(name).file_name().map(|file_name| file_name.to_string_lossy().to_string()))
+            .collect())
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), String> {
+        self.files.lock().remove(path)
+            .map(|_| ())
+            .ok_or_else(|| format!("File not found: {}", path))
+    }
+
+    fn rename_file(&self, from: &str, to: &str) -> Result<(), String> {
+        let mut files = self.files.lock();
+        let data = files.remove(from).ok_or_else(|| format!("File not found: {}", from))?;
+        files.insert(to.to_string(), data);
+        Ok(())
+    }
+}
+
+/// In-memory StorageBackend over a shared `MemFiles` map.
+pub struct MemStorage {
+    files: MemFiles,
+    dir_manager: MemDirManager,
+}
+
+impl MemStorage {
+    pub fn new(files: MemFiles) -> Self {
+        Self {
+            dir_manager: MemDirManager::new(files.clone()),
+            files,
+        }
+    }
+
+    /// The shared map holding the files of this storage.
+    pub fn files(&self) -> &MemFiles {
+        &self.files
+    }
+}
+
+impl Default for MemStorage {
+    fn default() -> Self {
+        Self::new(MemFiles::default())
+    }
+}
+
+impl StorageBackend for MemStorage {
+    fn dir_manager(&self) -> &dyn DirManager {
+        &self.dir_manager
+    }
+
+    fn open_file_reader(&self, path: &str) -> Result<Box<dyn FileReader>, String> {
+        Ok(Box::new(MemFileReader::open(self.files.clone(), path)?))
+    }
+
+    fn open_file_writer(&self, path: &str) -> Result<Box<dyn FileWriter>, String> {
+        Ok(Box::new(MemFileWriter::create(self.files.clone(), path)?))
+    }
+}
@@ -4,6 +4,8 @@ use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
+use memmap2::Mmap;
+
 use super::{FileReader, FileWriter, DirManager};
 
 /// Native implementation of FileReader using std::fs::File.
@@ -32,6 +34,40 @@ impl FileReader for NativeFileReader {
     }
 }
 
+/// Native implementation of FileReader over a read-only memory map of the file.
+///
+/// Each read is a bounds-checked copy out of the mapping instead of a seek and
+/// a read system call. The file must not be truncated while the reader is open.
+pub struct MmapFileReader {
+    mmap: Mmap,
+}
+
+impl MmapFileReader {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        // Safety: the mapping is read-only, and callers are told not to truncate the
+        // file while the reader is open. The mapping outlives the file handle.
+        let mmap = unsafe { Mmap::map(&file) }.map_err(|e| e.to_string())?;
+        Ok(Self { mmap })
+    }
+}
+
+impl FileReader for MmapFileReader {
+    fn file_size(&self) -> Result<u64, String> {
+        Ok(self.mmap.len() as u64)
+    }
+
+    fn read_chunk(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, String> {
+        if offset >= self.mmap.len() as u64 {
+            return Ok(0);
+        }
+        let start = offset as usize;
+        let len = buf.len().min(self.mmap.len() - start);
+        buf[..len].copy_from_slice(&self.mmap[start..start + len]);
+        Ok(len)
+    }
+}
+
 /// Native implementation of FileWriter using std::fs::File.
 pub struct NativeFileWriter {
     file: File,
@@ -58,6 +94,11 @@ impl FileWriter for NativeFileWriter {
     fn flush(&mut self) -> Result<(), String> {
         self.file.flush().map_err(|e| e.to_string())
     }
+
+    // The file was just created at offset 0 and std::fs::File is unbuffered
+    fn write_all_and_sync(&mut self, data: &[u8]) -> Result<(), String> {
+        self.file.write_all(data).map_err(|e| e.to_string())
+    }
 }
 
 /// Native implementation of DirManager using std::fs::create_dir_all.
@@ -88,4 +129,27 @@ impl DirManager for NativeDirManager {
         }
         Ok(count)
     }
-}
\ No newline at end of file
+
+    fn list_files(&self, path: &str) -> Result<Vec<String>, String> {
+        let dir = Path::new(path);
+        let mut names = Vec::new();
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read directory: {}", e))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to access directory entry: {}", e))?;
+            if entry.path().is_file() {
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        Ok(names)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), String> {
+        std::fs::remove_file(Path::new(path)).map_err(|e| e.to_string())
+    }
+
+    fn rename_file(&self, from: &str, to: &str) -> Result<(), String> {
+        std::fs::rename(Path::new(from), Path::new(to)).map_err(|e| e.to_string())
+    }
+}
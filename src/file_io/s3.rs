@@ -0,0 +1,315 @@
+//! S3-compatible object storage implementations of FileReader, FileWriter,
+//! DirManager and StorageBackend.
+//!
+//! A path maps to `bucket/key`: its first component names the bucket and the rest
+//! is the object key. Directories are key prefixes, as in `mem`.
+//!
+//! Objects can't be written in place, so a writer collects its chunks and uploads
+//! the object on `flush`. Chunks must come in order: symbols, written once at
+//! offset 0, work as they are, and so does a decoded output file, whose blocks are
+//! written in file order, but it is held in memory until the upload. An offset
+//! that skips ahead or goes back is rejected.
+//!
+//! Each upload sends the whole object, so a flush after every block re-uploads the
+//! growing output once per block. Decode to S3 without `DecodeOptions::stream`,
+//! whose point is flushing after every block, so the output is uploaded once.
+//!
+//! The backend reaches the store through `ObjectClient`, implemented here for
+//! `aws_sdk_s3::Client` and open to any other S3-compatible client.
+
+use std::io;
+use std::sync::Arc;
+
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::primitives::ByteStream;
+use once_cell::sync::OnceCell;
+use tokio::runtime::Runtime;
+
+use super::{DirManager, FileReader, FileWriter, StorageBackend};
+
+/// Blocking object store operations the S3 backend is built on.
+pub trait ObjectClient: Send + Sync {
+    /// Returns the size of the object, or None if there is no such object.
+    fn head_object(&self, bucket: &str, key: &str) -> Result<Option<u64>, String>;
+
+    /// Returns up to `len` bytes of the object starting at `offset`.
+    fn get_object_range(&self, bucket: &str, key: &str, offset: u64, len: usize) -> Result<Vec<u8>, String>;
+
+    /// Creates or replaces the object.
+    fn put_object(&self, bucket: &str, key: &str, data: Vec<u8>) -> Result<(), String>;
+
+    /// Returns the keys of all objects whose key starts with `prefix`.
+    fn list_keys(&self, bucket: &str, prefix: &str) -> Result<Vec<String>, String>;
+
+    /// Deletes the object.
+    fn delete_object(&self, bucket: &str, key: &str) -> Result<(), String>;
+}
+
+// Runs the SDK's requests for the blocking `ObjectClient` calls, from whichever thread makes them
+static RUNTIME: OnceCell<Runtime> = OnceCell::new();
+
+// The runtime, started on first use
+fn runtime() -> io::Result<&'static Runtime> {
+    RUNTIME.get_or_try_init(|| {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| io::Error::new(e.kind(), format!("Failed to start the S3 client runtime: {}", e)))
+    })
+}
+
+// The runtime, for the `ObjectClient` calls
+fn client_runtime() -> Result<&'static Runtime, String> {
+    runtime().map_err(|e| e.to_string())
+}
+
+/// `ObjectClient` over the AWS SDK. Each call blocks on the request, so it must
+/// not be made from within an async task.
+impl ObjectClient for aws_sdk_s3::Client {
+    fn head_object(&self, bucket: &str, key: &str) -> Result<Option<u64>, String> {
+        match client_runtime()?.block_on(self.head_object().bucket(bucket).key(key).send()) {
+            Ok(output) => Ok(Some(output.content_length().unwrap_or(0).max(0) as u64)),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(None),
+            Err(e) => Err(format!("Failed to head {}/{}: {}", bucket, key, DisplayErrorContext(e))),
+        }
+    }
+
+    fn get_object_range(&self, bucket: &str, key: &str, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        let range = format!("bytes={}-{}", offset, offset + len as u64 - 1);
+        client_runtime()?.block_on(async {
+            let output = self.get_object().bucket(bucket).key(key).range(range).send().await
+                .map_err(|e| format!("Failed to get {}/{}: {}", bucket, key, DisplayErrorContext(e)))?;
+            let data = output.body.collect().await
+                .map_err(|e| format!("Failed to read {}/{}: {}", bucket, key, e))?;
+            Ok(data.into_bytes().to_vec())
+        })
+    }
+
+    fn put_object(&self, bucket: &str, key: &str, data: Vec<u8>) -> Result<(), String> {
+        client_runtime()?.block_on(self.put_object().bucket(bucket).key(key).body(ByteStream::from(data)).send())
+            .map(|_| ())
+            .map_err(|e| format!("Failed to put {}/{}: {}", bucket, key, DisplayErrorContext(e)))
+    }
+
+    fn list_keys(&self, bucket: &str, prefix: &str) -> Result<Vec<String>, String> {
+        client_runtime()?.block_on(async {
+            let mut pages = self.list_objects_v2().bucket(bucket).prefix(prefix).into_paginator().send();
+            let mut keys = Vec::new();
+            while let Some(page) = pages.next().await {
+                let page = page
+                    .map_err(|e| format!("Failed to list {}/{}: {}", bucket, prefix, DisplayErrorContext(e)))?;
+                keys.extend(page.contents().iter().filter_map(|object| object.key().map(str::to_string)));
+            }
+            Ok(keys)
+        })
+    }
+
+    fn delete_object(&self, bucket: &str, key: &str) -> Result<(), String> {
+        client_runtime()?.block_on(self.delete_object().bucket(bucket).key(key).send())
+            .map(|_| ())
+            .map_err(|e| format!("Failed to delete {}/{}: {}", bucket, key, DisplayErrorContext(e)))
+    }
+}
+
+// Split a path into its bucket and object key
+fn split_path(path: &str) -> Result<(&str, &str), String> {
+    let path = path.trim_start_matches('/');
+    let (bucket, key) = path.split_once('/').unwrap_or((path, ""));
+    if bucket.is_empty() {
+        return Err(format!("No bucket in the path: {}", path));
+    }
+    Ok((bucket, key))
+}
+
+// Key prefix of the objects inside a directory, "" for the bucket root
+fn dir_prefix(key: &str) -> String {
+    let key = key.trim_end_matches('/');
+    if key.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", key)
+    }
+}
+
+/// S3 implementation of FileReader, fetching each chunk with a ranged GET.
+pub struct S3FileReader {
+    client: Arc<dyn ObjectClient>,
+    bucket: String,
+    key: String,
+    size: u64,
+}
+
+impl S3FileReader {
+    pub fn open(client: Arc<dyn ObjectClient>, path: &str) -> Result<Self, String> {
+        let (bucket, key) = split_path(path)?;
+        let size = client.head_object(bucket, key)?
+            .ok_or_else(|| format!("File not found: {}", path))?;
+        Ok(Self { bucket: bucket.to_string(), key: key.to_string(), client, size })
+    }
+}
+
+impl FileReader for S3FileReader {
+    fn file_size(&self) -> Result<u64, String> {
+        Ok(self.size)
+    }
+
+    fn read_chunk(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, String> {
+        if offset >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+        let len = std::cmp::min(buf.len() as u64, self.size - offset) as usize;
+        let data = self.client.get_object_range(&self.bucket, &self.key, offset, len)?;
+        let n = std::cmp::min(data.len(), len);
+        buf[..n].copy_from_slice(&data[..n]);
+        Ok(n)
+    }
+}
+
+/// S3 implementation of FileWriter. Chunks must be written in order; the object
+/// is uploaded on `flush`, so dropping the writer before then discards them. A
+/// flush with nothing written since the last upload doesn't upload again.
+pub struct S3FileWriter {
+    client: Arc<dyn ObjectClient>,
+    bucket: String,
+    key: String,
+    data: Vec<u8>,
+    // Length of `data` at the last upload; chunks only append, so a match means nothing changed
+    uploaded_len: Option<usize>,
+}
+
+impl S3FileWriter {
+    pub fn create(client: Arc<dyn ObjectClient>, path: &str) -> Result<Self, String> {
+        let (bucket, key) = split_path(path)?;
+        if key.is_empty() {
+            return Err(format!("No object key in the path: {}", path));
+        }
+        Ok(Self { bucket: bucket.to_string(), key: key.to_string(), client, data: Vec::new(), uploaded_len: None })
+    }
+}
+
+impl FileWriter for S3FileWriter {
+    fn write_chunk(&mut self, offset: usize, data: &[u8]) -> Result<(), String> {
+        if offset != self.data.len() {
+            return Err(format!(
+                "Objects are written sequentially: {}/{} has {} bytes, got a write at offset {}",
+                self.bucket, self.key, self.data.len(), offset
+            ));
+        }
+        self.data.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        if self.uploaded_len == Some(self.data.len()) {
+            return Ok(());
+        }
+        self.client.put_object(&self.bucket, &self.key, self.data.clone())?;
+        self.uploaded_len = Some(self.data.len());
+        Ok(())
+    }
+
+    // The whole object at once, uploaded without keeping a copy
+    fn write_all_and_sync(&mut self, data: &[u8]) -> Result<(), String> {
+        self.data.clear();
+        self.uploaded_len = None;
+        self.client.put_object(&self.bucket, &self.key, data.to_vec())
+    }
+}
+
+/// S3 implementation of DirManager over key prefixes.
+pub struct S3DirManager {
+    client: Arc<dyn ObjectClient>,
+}
+
+impl S3DirManager {
+    pub fn new(client: Arc<dyn ObjectClient>) -> Self {
+        Self { client }
+    }
+
+    // Names of the objects directly inside a directory
+    fn file_names(&self, path: &str) -> Result<Vec<String>, String> {
+        let (bucket, key) = split_path(path)?;
+        let prefix = dir_prefix(key);
+        Ok(self.client.list_keys(bucket, &prefix)?
+            .into_iter()
+            .filter_map(|object_key| {
+                let name = object_key.strip_prefix(&prefix)?;
+                (!name.is_empty() && !name.contains('/')).then(|| name.to_string())
+            })
+            .collect())
+    }
+}
+
+impl DirManager for S3DirManager {
+    fn create_dir_all(&self, path: &str) -> Result<(), String> {
+        // Prefixes exist as soon as an object is stored under them
+        split_path(path).map(|_| ())
+    }
+
+    fn dir_exists(&self, path: &str) -> Result<bool, String> {
+        let (bucket, key) = split_path(path)?;
+        Ok(!self.client.list_keys(bucket, &dir_prefix(key))?.is_empty())
+    }
+
+    fn count_files(&self, path: &str) -> Result<usize, String> {
+        Ok(self.file_names(path)?.len())
+    }
+
+    fn list_files(&self, path: &str) -> Result<Vec<String>, String> {
+        self.file_names(path)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), String> {
+        let (bucket, key) = split_path(path)?;
+        if self.client.head_object(bucket, key)?.is_none() {
+            return Err(format!("File not found: {}", path));
+        }
+        self.client.delete_object(bucket, key)
+    }
+
+    fn rename_file(&self, from: &str, to: &str) -> Result<(), String> {
+        let (from_bucket, from_key) = split_path(from)?;
+        let (to_bucket, to_key) = split_path(to)?;
+        let size = self.client.head_object(from_bucket, from_key)?
+            .ok_or_else(|| format!("File not found: {}", from))?;
+
+        // Object stores have no rename: copy, then delete the original
+        let data = self.client.get_object_range(from_bucket, from_key, 0, size as usize)?;
+        self.client.put_object(to_bucket, to_key, data)?;
+        self.client.delete_object(from_bucket, from_key)
+    }
+}
+
+/// StorageBackend over an S3-compatible object store.
+pub struct S3Storage {
+    client: Arc<dyn ObjectClient>,
+    dir_manager: S3DirManager,
+}
+
+impl S3Storage {
+    /// Fails if the runtime the AWS SDK client runs its requests on can't be started.
+    pub fn new(client: Arc<dyn ObjectClient>) -> io::Result<Self> {
+        runtime()?;
+        Ok(Self {
+            dir_manager: S3DirManager::new(client.clone()),
+            client,
+        })
+    }
+}
+
+impl StorageBackend for S3Storage {
+    fn dir_manager(&self) -> &dyn DirManager {
+        &self.dir_manager
+    }
+
+    fn open_file_reader(&self, path: &str) -> Result<Box<dyn FileReader>, String> {
+        Ok(Box::new(S3FileReader::open(self.client.clone(), path)?))
+    }
+
+    fn open_file_writer(&self, path: &str) -> Result<Box<dyn FileWriter>, String> {
+        Ok(Box::new(S3FileWriter::create(self.client.clone(), path)?))
+    }
+}
@@ -1,4 +1,8 @@
 //! WASM/browser implementations of FileReader, FileWriter, and DirManager traits.
+//!
+//! The synchronous FileReader and FileWriter rely on blocking `sync*` functions the
+//! host puts on the global scope. AsyncFileReader and AsyncFileWriter await the
+//! promises of `browser_fs.js` instead, and are what RaptorQSession uses.
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
@@ -6,7 +10,7 @@ use js_sys::Uint8Array;
 use wasm_bindgen_futures::JsFuture;
 use std::cell::RefCell;
 
-use super::{FileReader, FileWriter, DirManager};
+use super::{AsyncFileReader, AsyncFileWriter, FileReader, FileWriter, DirManager};
 
 /// JS glue for browser file I/O (see browser_fs.js)
 // Mark this module as not for FFI to prevent cbindgen from including it
@@ -88,21 +92,8 @@ impl FileReader for BrowserFileReader {
     }
 
     fn read_chunk(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, String> {
-        // We need to use wasm_bindgen_futures::spawn_local for async operations in WASM
-        // But since this function is synchronous, we'll need to use a different approach
-        
-        // Get a promise for the chunk read operation
-        let promise = js_read_chunk(&self.path, offset as u32, buf.len() as u32);
-        
-        // Convert to a JsFuture for easier handling
-        // Note: We're not using this future directly as we're using synchronous JS functions instead
-        let _future = JsFuture::from(promise);
-        
-        // In WASM, we can't use block_on, so we need to make this operation synchronous
-        // using a workaround. This is a simplified implementation for demonstration.
-        // In a real-world application, you'd want to refactor to use async/await patterns.
-        
-        // This is a synchronous call to JavaScript, which works in WASM context
+        // WASM can't block on a promise, so the synchronous API relies on the host's
+        // syncReadChunk. RaptorQSession goes through AsyncFileReader instead.
         let result = js_sys::Reflect::get(
             &js_sys::global(),
             &JsValue::from_str("syncReadChunk")
@@ -140,6 +131,26 @@ impl FileReader for BrowserFileReader {
     }
 }
 
+impl AsyncFileReader for BrowserFileReader {
+    fn file_size(&self) -> Result<u64, String> {
+        Ok(js_file_size(&self.path) as u64)
+    }
+
+    async fn read_chunk(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, String> {
+        let result = JsFuture::from(js_read_chunk(&self.path, offset as u32, buf.len() as u32))
+            .await
+            .map_err(|e| format!("JS error: {:?}", e))?;
+
+        let bytes = result.dyn_into::<Uint8Array>()
+            .map_err(|e| format!("Conversion error: {:?}", e))?;
+
+        // The JS side should respect the length we pass, but never overrun `buf`
+        let bytes_read = std::cmp::min(bytes.length() as usize, buf.len());
+        bytes.subarray(0, bytes_read as u32).copy_to(&mut buf[..bytes_read]);
+        Ok(bytes_read)
+    }
+}
+
 /// Browser implementation of FileWriter.
 pub struct BrowserFileWriter {
     path: String,
@@ -191,6 +202,29 @@ impl FileWriter for BrowserFileWriter {
         
         Ok(())
     }
+
+    // syncWriteChunk stores the data as it goes and syncFlushFile is a no-op in the
+    // bundled shims, so a whole file is one call into JS rather than two
+    fn write_all_and_sync(&mut self, data: &[u8]) -> Result<(), String> {
+        FileWriter::write_chunk(self, 0, data)
+    }
+}
+
+impl AsyncFileWriter for BrowserFileWriter {
+    async fn write_chunk(&mut self, offset: usize, data: &[u8]) -> Result<(), String> {
+        let array = Uint8Array::from(data);
+        JsFuture::from(js_write_chunk(&self.path, offset as u32, &array))
+            .await
+            .map_err(|e| format!("JS error: {:?}", e))?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        JsFuture::from(js_flush_file(&self.path))
+            .await
+            .map_err(|e| format!("JS error: {:?}", e))?;
+        Ok(())
+    }
 }
 
 /// Browser implementation of DirManager.
@@ -220,7 +254,82 @@ impl DirManager for BrowserDirManager {
         Ok(js_dir_exists(path))
     }
 
-    fn count_files(&self, _path: &str) -> Result<usize, String> {
-        Ok(0) // Placeholder, as counting files in a directory is not implemented
+    fn count_files(&self, path: &str) -> Result<usize, String> {
+        // Similar to other operations, we need a synchronous approach
+        let result = js_sys::Reflect::get(
+            &js_sys::global(),
+            &JsValue::from_str("syncCountFiles")
+        ).map_err(|e| format!("Failed to get syncCountFiles: {:?}", e))?;
+
+        let sync_count_files = result.dyn_ref::<js_sys::Function>()
+            .ok_or_else(|| "syncCountFiles is not a function".to_string())?;
+
+        let result = sync_count_files.call1(
+            &JsValue::NULL,
+            &JsValue::from_str(path),
+        ).map_err(|e| format!("JS error: {:?}", e))?;
+
+        let count = result.as_f64()
+            .ok_or_else(|| format!("Expected a number of files, got {:?}", result))?;
+
+        Ok(count as usize)
+    }
+
+    fn list_files(&self, path: &str) -> Result<Vec<String>, String> {
+        // Similar to other operations, we need a synchronous approach
+        let result = js_sys::Reflect::get(
+            &js_sys::global(),
+            &JsValue::from_str("syncListFiles")
+        ).map_err(|e| format!("Failed to get syncListFiles: {:?}", e))?;
+
+        let sync_list_files = result.dyn_ref::<js_sys::Function>()
+            .ok_or_else(|| "syncListFiles is not a function".to_string())?;
+
+        let result = sync_list_files.call1(
+            &JsValue::NULL,
+            &JsValue::from_str(path),
+        ).map_err(|e| format!("JS error: {:?}", e))?;
+
+        let names = result.dyn_into::<js_sys::Array>()
+            .map_err(|e| format!("Conversion error: {:?}", e))?;
+
+        Ok(names.iter().filter_map(|name| name.as_string()).collect())
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), String> {
+        // Similar to other operations, we need a synchronous approach
+        let result = js_sys::Reflect::get(
+            &js_sys::global(),
+            &JsValue::from_str("syncRemoveFile")
+        ).map_err(|e| format!("Failed to get syncRemoveFile: {:?}", e))?;
+
+        let sync_remove_file = result.dyn_ref::<js_sys::Function>()
+            .ok_or_else(|| "syncRemoveFile is not a function".to_string())?;
+
+        sync_remove_file.call1(
+            &JsValue::NULL,
+            &JsValue::from_str(path),
+        ).map_err(|e| format!("JS error: {:?}", e))?;
+
+        Ok(())
+    }
+
+    fn rename_file(&self, from: &str, to: &str) -> Result<(), String> {
+        // Similar to other operations, we need a synchronous approach
+        let result = js_sys::Reflect::get(
+            &js_sys::global(),
+            &JsValue::from_str("syncRenameFile")
+        ).map_err(|e| format!("Failed to get syncRenameFile: {:?}", e))?;
+
+        let sync_rename_file = result.dyn_ref::<js_sys::Function>()
+            .ok_or_else(|| "syncRenameFile is not a function".to_string())?;
+
+        sync_rename_file.call2(
+            &JsValue::NULL,
+            &JsValue::from_str(from),
+            &JsValue::from_str(to),
+        ).map_err(|e| format!("JS error: {:?}", e))?;
+
+        Ok(())
     }
 }
\ No newline at end of file
@@ -1,36 +1,142 @@
 pub mod processor;
 pub mod file_io;
+pub mod symbol_store;
 
 // Import wasm_browser module
 #[cfg(all(target_arch = "wasm32", feature = "browser-wasm"))]
 pub mod wasm_browser;
 
 // Re-export key types for simpler imports
-pub use processor::{ProcessorConfig, RaptorQProcessor, ProcessResult, ProcessError};
+pub use processor::{ProcessorConfig, RaptorQProcessor, ProcessResult, ProcessError, DecodeOptions, EncodeOptions, SymbolStorage, SymbolId, BlockId, FileEntry, SymbolManifest, DryRunReport, BlockDryRun, VerifyReport, validate_oti, CancelToken, HashAlgorithm, FileAttributes, LAYOUT_FORMAT_VERSION, MAX_SYMBOL_SIZE_B, PACKET_HEADER_SIZE_B};
+
+// Re-export the raptorq types needed to build encoder parameters by hand
+pub use raptorq::{EncodingPacket, ObjectTransmissionInformation};
 
 // Re-export RaptorQSession for WASM builds
 #[cfg(all(target_arch = "wasm32", feature = "browser-wasm"))]
 pub use wasm_browser::browser_wasm::RaptorQSession;
 
 use once_cell::sync::Lazy;
-use parking_lot::Mutex;
-use std::collections::HashMap;
-use std::ffi::{c_char, CStr, CString};
+use parking_lot::{Condvar, Mutex, MutexGuard};
+use std::collections::{HashMap, HashSet};
+use std::ffi::{c_char, CStr};
+use std::ops::Deref;
 use std::ptr;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use raptorq::PayloadId;
 
 // Global session counter for unique IDs
 static SESSION_COUNTER: AtomicUsize = AtomicUsize::new(1);
 
-// Global processor storage
-static PROCESSORS: Lazy<Mutex<HashMap<usize, RaptorQProcessor>>> = Lazy::new(|| {
+// Maximum number of sessions open at once, 0 for no limit
+static MAX_SESSIONS: AtomicUsize = AtomicUsize::new(0);
+
+// Global processor storage. Calls clone a session's processor out of the map and
+// release the lock before running, so one session's work never blocks another's.
+static PROCESSORS: Lazy<Mutex<HashMap<usize, Arc<RaptorQProcessor>>>> = Lazy::new(|| {
     // Initialize logging
     env_logger::init();
     Mutex::new(HashMap::new())
 });
 
+// Sessions with a raptorq_reconfigure_session pending; only changed under the PROCESSORS lock
+static RECONFIGURING: Lazy<Mutex<HashSet<usize>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+// Signalled, with the PROCESSORS lock, whenever a reconfiguration finishes
+static RECONFIGURED: Condvar = Condvar::new();
+
+// Signalled, with the PROCESSORS lock, when a call on a session being reconfigured
+// releases its processor
+static CALL_RETURNED: Condvar = Condvar::new();
+
+// A session's processor, held for the length of an FFI call
+struct SessionProcessor {
+    session_id: usize,
+    // Only taken when dropped
+    processor: Option<Arc<RaptorQProcessor>>,
+}
+
+impl Deref for SessionProcessor {
+    type Target = RaptorQProcessor;
+
+    fn deref(&self) -> &RaptorQProcessor {
+        self.processor.as_ref().expect("The processor is only released on drop")
+    }
+}
+
+impl Drop for SessionProcessor {
+    fn drop(&mut self) {
+        // Released under the map lock, so a pending reconfiguration sees the
+        // reference gone when it wakes up
+        let _processors = PROCESSORS.lock();
+        self.processor.take();
+        if RECONFIGURING.lock().contains(&self.session_id) {
+            CALL_RETURNED.notify_all();
+        }
+    }
+}
+
+// Wait on one of the conditions signalled with the PROCESSORS lock, until `deadline`
+// if there is one. Returns whether it timed out.
+fn wait_on(
+    condvar: &Condvar,
+    processors: &mut MutexGuard<'_, HashMap<usize, Arc<RaptorQProcessor>>>,
+    deadline: Option<Instant>,
+) -> bool {
+    match deadline {
+        Some(deadline) => condvar.wait_until(processors, deadline).timed_out(),
+        None => {
+            condvar.wait(processors);
+            false
+        }
+    }
+}
+
+// Look up a session's processor, holding the lock only for the lookup. Waits while
+// the session is being reconfigured, so the call runs with the new settings.
+fn get_processor(session_id: usize) -> Option<SessionProcessor> {
+    let mut processors = PROCESSORS.lock();
+    while RECONFIGURING.lock().contains(&session_id) {
+        RECONFIGURED.wait(&mut processors);
+    }
+    let processor = processors.get(&session_id).cloned()?;
+    Some(SessionProcessor { session_id, processor: Some(processor) })
+}
+
+// Copy `s` into a caller's non-null buffer of `len` bytes as a NUL-terminated C string.
+// Returns 0 on success, -3 if `s` holds a NUL byte (C would see it cut short) and
+// -4 if the buffer can't hold `s` and its terminator; nothing is written on failure.
+fn write_cstring_to_buffer(s: &str, buf: *mut c_char, len: usize) -> i32 {
+    if s.as_bytes().contains(&0) {
+        return -3;
+    }
+    if s.len() >= len {
+        return -4;
+    }
+
+    unsafe {
+        ptr::copy_nonoverlapping(s.as_ptr() as *const c_char, buf, s.len());
+        *buf.add(s.len()) = 0;
+    }
+    0
+}
+
+/// Caps the number of sessions that can be open at once
+///
+/// Once `max_sessions` sessions are open, raptorq_init_session returns 0 until one
+/// is freed with raptorq_free_session. 0 removes the cap, which is the default.
+/// Sessions already open are kept when the cap is lowered below their number.
+#[unsafe(no_mangle)]
+pub extern "C" fn raptorq_set_max_sessions(max_sessions: usize) {
+    MAX_SESSIONS.store(max_sessions, Ordering::SeqCst);
+}
+
 /// Initializes a RaptorQ session with the given configuration
-/// Returns a session ID on success, or 0 on failure
+/// Returns a session ID on success, or 0 on failure (e.g. an invalid symbol size:
+/// it must be 1 to 63, a multiple of 8 from 64 to 65528, or the 65535 default, or
+/// the cap set with raptorq_set_max_sessions is reached)
 #[unsafe(no_mangle)]
 pub extern "C" fn raptorq_init_session(
     symbol_size: u16,
@@ -38,19 +144,26 @@ pub extern "C" fn raptorq_init_session(
     max_memory_mb: u64,
     concurrency_limit: u64,
 ) -> usize {
-    let session_id = SESSION_COUNTER.fetch_add(1, Ordering::SeqCst);
-
     let config = ProcessorConfig {
         symbol_size,
         redundancy_factor,
         max_memory_mb,
         concurrency_limit,
+        ..ProcessorConfig::default()
     };
+    if config.validate().is_err() {
+        return 0;
+    }
 
-    let processor = RaptorQProcessor::new(config);
-
+    // Checked and inserted under the same lock, so concurrent inits can't overshoot the cap
     let mut processors = PROCESSORS.lock();
-    processors.insert(session_id, processor);
+    let max_sessions = MAX_SESSIONS.load(Ordering::SeqCst);
+    if max_sessions != 0 && processors.len() >= max_sessions {
+        return 0;
+    }
+
+    let session_id = SESSION_COUNTER.fetch_add(1, Ordering::SeqCst);
+    processors.insert(session_id, Arc::new(RaptorQProcessor::new(config)));
 
     session_id
 }
@@ -62,6 +175,166 @@ pub extern "C" fn raptorq_free_session(session_id: usize) -> bool {
     processors.remove(&session_id).is_some()
 }
 
+/// Changes the configuration of an open session, keeping its session ID
+///
+/// Takes the same settings as raptorq_init_session; the session's other settings
+/// are kept. Waits for the calls running on the session to return first; calls
+/// made on the session meanwhile wait for the reconfiguration to finish, and then
+/// run with the new settings.
+///
+/// Returns true on success, false on invalid session or an invalid configuration
+/// (the session keeps its previous configuration). Use
+/// raptorq_reconfigure_session_timeout to bound the wait.
+#[unsafe(no_mangle)]
+pub extern "C" fn raptorq_reconfigure_session(
+    session_id: usize,
+    symbol_size: u16,
+    redundancy_factor: u8,
+    max_memory_mb: u64,
+    concurrency_limit: u64,
+) -> bool {
+    raptorq_reconfigure_session_timeout(
+        session_id, symbol_size, redundancy_factor, max_memory_mb, concurrency_limit, u64::MAX,
+    ) == 0
+}
+
+/// Changes the configuration of an open session, keeping its session ID
+///
+/// As raptorq_reconfigure_session, but waits at most `timeout_ms` for the calls
+/// running on the session to return.
+///
+/// Returns:
+/// *   0 on success
+/// *  -2 on an invalid configuration (the session keeps its previous configuration)
+/// *  -5 on invalid session
+/// * -18 on timeout (calls still running, or another reconfiguration pending)
+#[unsafe(no_mangle)]
+pub extern "C" fn raptorq_reconfigure_session_timeout(
+    session_id: usize,
+    symbol_size: u16,
+    redundancy_factor: u8,
+    max_memory_mb: u64,
+    concurrency_limit: u64,
+    timeout_ms: u64,
+) -> i32 {
+    // No deadline if it is too far off to represent
+    let deadline = Instant::now().checked_add(Duration::from_millis(timeout_ms));
+
+    // Mark the session as pending, so new calls on it wait instead of taking a
+    // reference that would keep the config from being swapped
+    let mut processors = PROCESSORS.lock();
+    while RECONFIGURING.lock().contains(&session_id) {
+        if wait_on(&RECONFIGURED, &mut processors, deadline) {
+            return -18;
+        }
+    }
+    if !processors.contains_key(&session_id) {
+        return -5;
+    }
+    RECONFIGURING.lock().insert(session_id);
+
+    // Calls already running hold their own reference to the processor; wait until
+    // none is left so the config is never swapped under a running task
+    let result = loop {
+        let processor = match processors.get_mut(&session_id) {
+            Some(p) => p,
+            None => break -5,
+        };
+
+        if let Some(processor) = Arc::get_mut(processor) {
+            let config = ProcessorConfig {
+                symbol_size,
+                redundancy_factor,
+                max_memory_mb,
+                concurrency_limit,
+                ..processor.get_config().clone()
+            };
+            break if processor.reconfigure(config).is_ok() { 0 } else { -2 };
+        }
+
+        // Woken by each call on the session releasing its processor
+        if wait_on(&CALL_RETURNED, &mut processors, deadline) {
+            break -18;
+        }
+    };
+
+    // Released under the map lock, so waiting calls can't miss the wakeup
+    RECONFIGURING.lock().remove(&session_id);
+    RECONFIGURED.notify_all();
+    result
+}
+
+/// Reads back the configuration of an open session
+///
+/// Arguments:
+/// * `session_id` - Session ID returned from raptorq_init_session
+/// * `out_symbol_size` - Receives the symbol size in bytes
+/// * `out_redundancy_factor` - Receives the redundancy factor
+/// * `out_max_memory_mb` - Receives the memory limit in MB
+/// * `out_concurrency_limit` - Receives the concurrency limit
+///
+/// Returns:
+/// *  0 on success
+/// * -2 on invalid parameters (a null pointer)
+/// * -5 on invalid session
+///
+/// # Safety
+///
+/// The caller must pass each `out_*` pointer pointing to a writable value of its type.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn raptorq_get_session_config(
+    session_id: usize,
+    out_symbol_size: *mut u16,
+    out_redundancy_factor: *mut u8,
+    out_max_memory_mb: *mut u64,
+    out_concurrency_limit: *mut u64,
+) -> i32 {
+    if out_symbol_size.is_null() || out_redundancy_factor.is_null()
+        || out_max_memory_mb.is_null() || out_concurrency_limit.is_null()
+    {
+        return -2;
+    }
+
+    let processor = match get_processor(session_id) {
+        Some(p) => p,
+        None => return -5,
+    };
+
+    let config = processor.get_config();
+    unsafe {
+        *out_symbol_size = config.symbol_size;
+        *out_redundancy_factor = config.redundancy_factor;
+        *out_max_memory_mb = config.max_memory_mb;
+        *out_concurrency_limit = config.concurrency_limit;
+    }
+
+    0
+}
+
+/// Stops a session from accepting new tasks and waits for its active tasks to finish
+///
+/// Arguments:
+/// * `session_id` - Session ID returned from raptorq_init_session
+/// * `timeout_ms` - Maximum time to wait for active tasks, in milliseconds
+///
+/// Returns:
+/// *   0 on success
+/// *  -5 on invalid session
+/// * -18 on timeout (tasks still running)
+#[unsafe(no_mangle)]
+pub extern "C" fn raptorq_shutdown_session(session_id: usize, timeout_ms: u64) -> i32 {
+    let processor = match get_processor(session_id) {
+        Some(p) => p,
+        None => return -5,
+    };
+
+    match processor.shutdown(Duration::from_millis(timeout_ms)) {
+        Ok(_) => 0,
+        Err(ProcessError::Timeout) => -18,
+        Err(_) => -1,
+    }
+}
+
 /// Encodes a file using RaptorQ - streaming implementation
 ///
 /// Arguments:
@@ -110,8 +383,7 @@ pub extern "C" fn raptorq_create_metadata(
         Err(_) => return -2,
     };
 
-    let processors = PROCESSORS.lock();
-    let processor = match processors.get(&session_id) {
+    let processor = match get_processor(session_id) {
         Some(p) => p,
         None => return -5,
     };
@@ -125,25 +397,7 @@ pub extern "C" fn raptorq_create_metadata(
             };
 
             // Copy result to result buffer
-            let c_result = match CString::new(result_json) {
-                Ok(s) => s,
-                Err(_) => return -3,
-            };
-
-            let result_bytes = c_result.as_bytes_with_nul();
-            if result_bytes.len() > result_buffer_len {
-                return -4;
-            }
-
-            unsafe {
-                ptr::copy_nonoverlapping(
-                    result_bytes.as_ptr() as *const c_char,
-                    result_buffer,
-                    result_bytes.len(),
-                );
-            }
-
-            0
+            write_cstring_to_buffer(&result_json, result_buffer, result_buffer_len)
         },
         Err(e) => match e {
             ProcessError::IOError(_) => -11,
@@ -205,8 +459,7 @@ pub extern "C" fn raptorq_encode_file(
         Err(_) => return -2,
     };
 
-    let processors = PROCESSORS.lock();
-    let processor = match processors.get(&session_id) {
+    let processor = match get_processor(session_id) {
         Some(p) => p,
         None => return -5,
     };
@@ -220,25 +473,86 @@ pub extern "C" fn raptorq_encode_file(
             };
 
             // Copy result to result buffer
-            let c_result = match CString::new(result_json) {
-                Ok(s) => s,
-                Err(_) => return -3,
-            };
+            write_cstring_to_buffer(&result_json, result_buffer, result_buffer_len)
+        },
+        Err(e) => match e {
+            ProcessError::IOError(_) => -11,
+            ProcessError::FileNotFound(_) => -12,
+            ProcessError::InvalidPath(_) => -13,
+            ProcessError::EncodingFailed(_) => -14,
+            ProcessError::MemoryLimitExceeded { .. } => -16,
+            ProcessError::ConcurrencyLimitReached => -17,
+            _ => -1,
+        },
+    }
+}
 
-            let result_bytes = c_result.as_bytes_with_nul();
-            if result_bytes.len() > result_buffer_len {
-                return -4;
-            }
+/// Encodes an in-memory buffer using RaptorQ
+///
+/// Arguments:
+/// * `session_id` - Session ID returned from raptorq_init_session
+/// * `data` - Pointer to the bytes to encode
+/// * `data_len` - Number of bytes at `data`
+/// * `output_dir` - Directory where symbols will be written
+/// * `block_size` - Size of blocks to process at once (0 = auto)
+/// * `result_buffer` - Buffer to store the result (JSON metadata)
+/// * `result_buffer_len` - Length of the result buffer
+///
+/// Returns:
+/// *   0 on success
+/// *  -1 on generic error
+/// *  -2 on invalid parameters
+/// *  -3 on invalid response
+/// *  -4 on bad return buffer size
+/// *  -5 on invalid session
+/// * -11 on IO error
+/// * -13 on Invalid Path
+/// * -14 on Encoding failed
+/// * -16 on Memory limit exceeded
+/// * -17 on Concurrency limit reached
+///
+/// # Safety
+///
+/// The caller must pass `data` pointing to at least `data_len` readable bytes,
+/// `output_dir` as a valid nul-terminated string and `result_buffer` pointing to at
+/// least `result_buffer_len` writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn raptorq_encode_buffer(
+    session_id: usize,
+    data: *const u8,
+    data_len: usize,
+    output_dir: *const c_char,
+    block_size: usize,
+    result_buffer: *mut c_char,
+    result_buffer_len: usize,
+) -> i32 {
+    // Basic null pointer checks
+    if data.is_null() || output_dir.is_null() || result_buffer.is_null() {
+        return -2;
+    }
 
-            unsafe {
-                ptr::copy_nonoverlapping(
-                    result_bytes.as_ptr() as *const c_char,
-                    result_buffer,
-                    result_bytes.len(),
-                );
-            }
+    let data = unsafe { std::slice::from_raw_parts(data, data_len) };
+
+    let output_dir_str = match unsafe { CStr::from_ptr(output_dir) }.to_str() {
+        Ok(s) => s,
+        Err(_) => return -2,
+    };
 
-            0
+    let processor = match get_processor(session_id) {
+        Some(p) => p,
+        None => return -5,
+    };
+
+    match processor.encode_buffer(data, output_dir_str, block_size) {
+        Ok(result) => {
+            // Serialize result to JSON
+            let result_json = match serde_json::to_string(&result) {
+                Ok(j) => j,
+                Err(_) => return -3,
+            };
+
+            // Copy result to result buffer
+            write_cstring_to_buffer(&result_json, result_buffer, result_buffer_len)
         },
         Err(e) => match e {
             ProcessError::IOError(_) => -11,
@@ -272,40 +586,29 @@ pub extern "C" fn raptorq_get_last_error(
         return -1;
     }
 
-    let processors = PROCESSORS.lock();
-    let processor = match processors.get(&session_id) {
+    let processor = match get_processor(session_id) {
         Some(p) => p,
         None => return -1,
     };
 
-    let error_msg = processor.get_last_error();
-    let c_error = match CString::new(error_msg) {
-        Ok(s) => s,
-        Err(_) => return -1,
-    };
+    if error_buffer_len == 0 {
+        return -1;
+    }
 
-    let error_bytes = c_error.as_bytes_with_nul();
-    if error_bytes.len() > error_buffer_len {
-        // Error message too long, truncate
-        unsafe {
-            ptr::copy_nonoverlapping(
-                error_bytes.as_ptr() as *const c_char,
-                error_buffer,
-                error_buffer_len - 1,
-            );
-            *error_buffer.add(error_buffer_len - 1) = 0;
-        }
-    } else {
-        unsafe {
-            ptr::copy_nonoverlapping(
-                error_bytes.as_ptr() as *const c_char,
-                error_buffer,
-                error_bytes.len(),
-            );
+    // Best effort: drop NUL bytes and cut the message to fit, on a character boundary
+    let mut error_msg = processor.get_last_error().replace('\0', "");
+    if error_msg.len() >= error_buffer_len {
+        let mut end = error_buffer_len - 1;
+        while !error_msg.is_char_boundary(end) {
+            end -= 1;
         }
+        error_msg.truncate(end);
     }
 
-    0
+    match write_cstring_to_buffer(&error_msg, error_buffer, error_buffer_len) {
+        0 => 0,
+        _ => -1,
+    }
 }
 
 /// Decodes RaptorQ symbols back to the original file
@@ -357,8 +660,7 @@ pub extern "C" fn raptorq_decode_symbols(
         Err(_) => return -2,
     };
 
-    let processors = PROCESSORS.lock();
-    let processor = match processors.get(&session_id) {
+    let processor = match get_processor(session_id) {
         Some(p) => p,
         None => return -5,
     };
@@ -370,11 +672,173 @@ pub extern "C" fn raptorq_decode_symbols(
             ProcessError::FileNotFound(_) => -12,
             ProcessError::InvalidPath(_) => -13,
             ProcessError::DecodingFailed(_) => -15,
+            ProcessError::MemoryLimitExceeded { .. } => -16,
+            ProcessError::ConcurrencyLimitReached => -17,
             _ => -1, // Generic error for unhandled cases
         },
     }
 }
 
+/// Size of the nul-terminated hash buffer in `BlockInfoC`
+pub const BLOCK_HASH_BUFFER_LEN: usize = 64;
+
+/// Metadata of one block of a layout, for C hosts that don't parse JSON
+#[repr(C)]
+pub struct BlockInfoC {
+    pub block_id: u64,
+    pub original_offset: u64,
+    pub size: u64,
+    pub symbols_count: u64,
+    /// Base58 hash of the block data, nul-terminated
+    pub hash: [c_char; BLOCK_HASH_BUFFER_LEN],
+}
+
+// Read and parse a layout file, returning the FFI error code on failure
+fn load_layout(layout_path: *const c_char) -> Result<processor::RaptorQLayout, i32> {
+    if layout_path.is_null() {
+        return Err(-2);
+    }
+    let layout_path_str = unsafe { CStr::from_ptr(layout_path) }.to_str().map_err(|_| -2)?;
+
+    let mut reader = file_io::open_file_reader(layout_path_str).map_err(|_| -12)?;
+    let size = reader.file_size().map_err(|_| -11)? as usize;
+    let mut contents = vec![0u8; size];
+    let bytes_read = file_io::read_full_chunk(reader.as_mut(), 0, &mut contents).map_err(|_| -11)?;
+    if bytes_read != size {
+        return Err(-11);
+    }
+
+    serde_json::from_slice(&contents).map_err(|_| -15)
+}
+
+/// Gets the number of blocks in a layout file
+///
+/// Arguments:
+/// * `layout_path` - Path to the layout file
+///
+/// Returns:
+/// * The number of blocks on success
+/// *  -2 on invalid parameters
+/// * -11 on IO error
+/// * -12 on File not found
+/// * -15 on invalid layout
+#[unsafe(no_mangle)]
+pub extern "C" fn raptorq_layout_block_count(layout_path: *const c_char) -> i64 {
+    match load_layout(layout_path) {
+        Ok(layout) => layout.blocks.len() as i64,
+        Err(code) => code as i64,
+    }
+}
+
+/// Gets the metadata of one block of a layout file
+///
+/// Arguments:
+/// * `layout_path` - Path to the layout file
+/// * `block_id` - ID of the block to describe
+/// * `out` - Struct to fill with the block's metadata
+///
+/// Returns:
+/// *   0 on success
+/// *  -2 on invalid parameters (including a block ID not in the layout)
+/// * -11 on IO error
+/// * -12 on File not found
+/// * -15 on invalid layout
+///
+/// # Safety
+///
+/// The caller must pass `layout_path` as a valid nul-terminated string and `out`
+/// pointing to a writable `BlockInfoC`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn raptorq_layout_block_info(
+    layout_path: *const c_char,
+    block_id: u64,
+    out: *mut BlockInfoC,
+) -> i32 {
+    if out.is_null() {
+        return -2;
+    }
+
+    let layout = match load_layout(layout_path) {
+        Ok(layout) => layout,
+        Err(code) => return code,
+    };
+
+    let block = match layout.blocks.iter().find(|block| block.block_id.index() as u64 == block_id) {
+        Some(block) => block,
+        None => return -2,
+    };
+
+    let hash_bytes = block.hash.as_bytes();
+    if hash_bytes.len() >= BLOCK_HASH_BUFFER_LEN {
+        return -15;
+    }
+    let mut hash = [0 as c_char; BLOCK_HASH_BUFFER_LEN];
+    for (dst, src) in hash.iter_mut().zip(hash_bytes) {
+        *dst = *src as c_char;
+    }
+
+    let symbols_count = match &block.symbols_manifest {
+        Some(manifest) => manifest.count,
+        None => block.symbols.len() as u64,
+    };
+
+    unsafe {
+        *out = BlockInfoC {
+            block_id,
+            original_offset: block.original_offset,
+            size: block.size,
+            symbols_count,
+            hash,
+        };
+    }
+
+    0
+}
+
+/// Gets the size of the file a layout decodes to, so hosts can check the space
+/// available before decoding
+///
+/// Arguments:
+/// * `session_id` - Session ID returned from raptorq_init_session
+/// * `layout_path` - Path to the layout file
+///
+/// Returns:
+/// * The decoded file size in bytes on success
+/// * -1 on error; for a valid session, the reason is kept as its last error
+#[unsafe(no_mangle)]
+pub extern "C" fn raptorq_decoded_size(session_id: usize, layout_path: *const c_char) -> i64 {
+    let processor = match get_processor(session_id) {
+        Some(p) => p,
+        None => return -1,
+    };
+
+    let layout = match load_layout(layout_path) {
+        Ok(layout) => layout,
+        Err(code) => {
+            let err = match code {
+                -2 => "Invalid layout path",
+                -12 => "Layout file not found",
+                -15 => "Invalid layout file",
+                _ => "Failed to read the layout file",
+            };
+            processor.set_last_error(err.to_string());
+            return -1;
+        },
+    };
+
+    // Blocks may be listed in any order, so the file ends where the furthest one does
+    let size = layout.blocks.iter()
+        .map(|block| block.original_offset.checked_add(block.size))
+        .try_fold(0u64, |end, block_end| block_end.map(|block_end| end.max(block_end)));
+    match size {
+        Some(size) if size <= i64::MAX as u64 => size as i64,
+        _ => {
+            processor.set_last_error("The layout's blocks end beyond the largest file size".to_string());
+            -1
+        },
+    }
+}
+
 /// Gets a recommended block size based on file size and available memory
 ///
 /// Arguments:
@@ -389,8 +853,7 @@ pub extern "C" fn raptorq_get_recommended_block_size(
     session_id: usize,
     file_size: u64,
 ) -> usize {
-    let processors = PROCESSORS.lock();
-    let processor = match processors.get(&session_id) {
+    let processor = match get_processor(session_id) {
         Some(p) => p,
         None => return 0,
     };
@@ -398,6 +861,107 @@ pub extern "C" fn raptorq_get_recommended_block_size(
     processor.get_recommended_block_size(file_size as usize)
 }
 
+/// Checks that a symbol's bytes hash to the ID it is stored under
+///
+/// IDs are checked as BLAKE3 hashes, the default. Symbols of a layout whose
+/// `hash_algorithm` is another hash must go through raptorq_verify_symbol_with_hash,
+/// as this function reports them all as mismatches.
+///
+/// Arguments:
+/// * `data` - Pointer to the serialized symbol
+/// * `data_len` - Number of bytes at `data`
+/// * `expected_id` - The symbol ID the bytes were stored under
+///
+/// Returns:
+/// *  1 if the symbol matches its ID
+/// *  0 if it does not
+/// * -2 on invalid parameters
+///
+/// # Safety
+///
+/// The caller must pass `data` pointing to at least `data_len` readable bytes and
+/// `expected_id` as a valid nul-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn raptorq_verify_symbol(
+    data: *const u8,
+    data_len: usize,
+    expected_id: *const c_char,
+) -> i32 {
+    if data.is_null() || expected_id.is_null() {
+        return -2;
+    }
+
+    let expected_id_str = match unsafe { CStr::from_ptr(expected_id) }.to_str() {
+        Ok(s) => s,
+        Err(_) => return -2,
+    };
+
+    let data = unsafe { std::slice::from_raw_parts(data, data_len) };
+    if processor::symbol_id_for(data).as_str() == expected_id_str {
+        1
+    } else {
+        0
+    }
+}
+
+/// Checks that a symbol's bytes hash to the ID it is stored under, with the given hash
+///
+/// Arguments:
+/// * `data` - Pointer to the serialized symbol
+/// * `data_len` - Number of bytes at `data`
+/// * `expected_id` - The symbol ID the bytes were stored under
+/// * `hash_algorithm` - The layout's `hash_algorithm`: "blake3" or "sha256"
+///
+/// Returns:
+/// *  1 if the symbol matches its ID
+/// *  0 if it does not
+/// * -2 on invalid parameters, including an unknown hash
+///
+/// # Safety
+///
+/// The caller must pass `data` pointing to at least `data_len` readable bytes, and
+/// `expected_id` and `hash_algorithm` as valid nul-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn raptorq_verify_symbol_with_hash(
+    data: *const u8,
+    data_len: usize,
+    expected_id: *const c_char,
+    hash_algorithm: *const c_char,
+) -> i32 {
+    if data.is_null() || expected_id.is_null() || hash_algorithm.is_null() {
+        return -2;
+    }
+
+    let expected_id_str = match unsafe { CStr::from_ptr(expected_id) }.to_str() {
+        Ok(s) => s,
+        Err(_) => return -2,
+    };
+    let hash_algorithm = match unsafe { CStr::from_ptr(hash_algorithm) }.to_str().ok().and_then(HashAlgorithm::from_name) {
+        Some(hash_algorithm) => hash_algorithm,
+        None => return -2,
+    };
+
+    let data = unsafe { std::slice::from_raw_parts(data, data_len) };
+    if hash_algorithm.hash_as_b58(data) == expected_id_str {
+        1
+    } else {
+        0
+    }
+}
+
+// Measured from `raptorq` itself rather than trusting `PACKET_HEADER_SIZE_B`
+static PACKET_HEADER_SIZE: Lazy<usize> = Lazy::new(|| {
+    let payload = [0u8; 1];
+    EncodingPacket::new(PayloadId::new(0, 0), payload.to_vec()).serialize().len() - payload.len()
+});
+
+/// Returns the number of bytes a serialized packet (symbol file) adds on top of
+/// the symbol data
+#[unsafe(no_mangle)]
+pub extern "C" fn raptorq_packet_header_size() -> usize {
+    *PACKET_HEADER_SIZE
+}
+
 /// Version information
 #[unsafe(no_mangle)]
 pub extern "C" fn raptorq_version(
@@ -409,25 +973,26 @@ pub extern "C" fn raptorq_version(
     }
 
     let version = "RaptorQ Library v0.1.0";
-    let c_version = match CString::new(version) {
-        Ok(s) => s,
-        Err(_) => return -1,
-    };
+    match write_cstring_to_buffer(version, version_buffer, version_buffer_len) {
+        0 => 0,
+        _ => -1,
+    }
+}
 
-    let version_bytes = c_version.as_bytes_with_nul();
-    if version_bytes.len() > version_buffer_len {
+/// Version of the `raptorq` crate compiled in, which defines the OTI and symbol format
+#[unsafe(no_mangle)]
+pub extern "C" fn raptorq_engine_version(
+    version_buffer: *mut c_char,
+    version_buffer_len: usize,
+) -> i32 {
+    if version_buffer.is_null() {
         return -1;
     }
 
-    unsafe {
-        ptr::copy_nonoverlapping(
-            version_bytes.as_ptr() as *const c_char,
-            version_buffer,
-            version_bytes.len(),
-        );
+    match write_cstring_to_buffer(processor::raptorq_engine_version(), version_buffer, version_buffer_len) {
+        0 => 0,
+        _ => -1,
     }
-
-    0
 }
 
 #[cfg(test)]
@@ -469,6 +1034,18 @@ mod ffi_tests {
             raptorq_free_session(session_id);
         }
     
+        #[test]
+        fn test_ffi_init_invalid_symbol_size() {
+            assert_eq!(raptorq_init_session(0, 10, 1024, 4), 0, "A zero symbol size should be rejected");
+
+            assert_eq!(raptorq_init_session(1001, 10, 1024, 4), 0, "A misaligned symbol size should be rejected");
+
+            // The 65535 default is still accepted
+            let session_id = raptorq_init_session(65535, 10, 1024, 4);
+            assert_ne!(session_id, 0, "The default symbol size should be accepted");
+            raptorq_free_session(session_id);
+        }
+
         #[test]
         fn test_ffi_init_multiple() {
             let session_id1 = raptorq_init_session(1024, 10, 1024, 4);
@@ -508,29 +1085,217 @@ mod ffi_tests {
             let processors = PROCESSORS.lock();
             assert!(!processors.contains_key(&session_id), "Session should not exist after freeing");
         }
-    
+    
+        #[test]
+        fn test_ffi_free_invalid_session() {
+            // Try to free a session that doesn't exist
+            let invalid_session_id = 99999;
+            let result = raptorq_free_session(invalid_session_id);
+            
+            assert!(!result, "Freeing invalid session should return false");
+        }
+    
+        #[test]
+        fn test_ffi_free_double_free() {
+            let session_id = init_test_session();
+            
+            // First free (should succeed)
+            let first_result = raptorq_free_session(session_id);
+            assert!(first_result, "First free should return true");
+            
+            // Second free (should fail)
+            let second_result = raptorq_free_session(session_id);
+            assert!(!second_result, "Second free of same ID should return false");
+        }
+    
+        // Tests for raptorq_reconfigure_session
+        #[test]
+        fn test_ffi_reconfigure_session() {
+            let session_id = init_test_session();
+
+            assert_eq!(raptorq_reconfigure_session_timeout(session_id, 2048, 6, 512, 2, 1000), 0);
+            {
+                let processors = PROCESSORS.lock();
+                let config = processors.get(&session_id).expect("Session should keep its ID").get_config();
+                assert_eq!(
+                    (config.symbol_size, config.redundancy_factor, config.max_memory_mb, config.concurrency_limit),
+                    (2048, 6, 512, 2)
+                );
+            }
+
+            // An invalid symbol size is rejected and the previous settings stay
+            assert_eq!(raptorq_reconfigure_session_timeout(session_id, 100, 8, 512, 2, 1000), -2);
+            {
+                let processors = PROCESSORS.lock();
+                let config = processors.get(&session_id).unwrap().get_config();
+                assert_eq!((config.symbol_size, config.redundancy_factor), (2048, 6));
+            }
+
+            raptorq_free_session(session_id);
+        }
+
+        #[test]
+        fn test_ffi_reconfigure_invalid_session() {
+            assert_eq!(raptorq_reconfigure_session_timeout(99999, 1024, 10, 1024, 4, 1000), -5);
+            assert!(!raptorq_reconfigure_session(99999, 1024, 10, 1024, 4));
+        }
+
+        #[test]
+        fn test_ffi_reconfigure_session_bool() {
+            let session_id = init_test_session();
+
+            assert!(raptorq_reconfigure_session(session_id, 2048, 6, 512, 2));
+            assert_eq!(get_processor(session_id).unwrap().get_config().redundancy_factor, 6);
+            assert!(!raptorq_reconfigure_session(session_id, 100, 8, 512, 2));
+            assert_eq!(get_processor(session_id).unwrap().get_config().redundancy_factor, 6);
+
+            raptorq_free_session(session_id);
+        }
+
+        #[test]
+        fn test_ffi_reconfigure_session_waits_for_running_calls() {
+            let session_id = init_test_session();
+
+            // A call still holding the processor keeps the config from being swapped
+            let running = get_processor(session_id).unwrap();
+            assert_eq!(raptorq_reconfigure_session_timeout(session_id, 2048, 6, 512, 2, 50), -18);
+            assert_eq!(get_processor(session_id).unwrap().get_config().redundancy_factor, 10);
+
+            // Calls made while the reconfiguration is pending wait for it, then see the new settings
+            let reconfigure = std::thread::spawn(move || {
+                raptorq_reconfigure_session_timeout(session_id, 2048, 6, 512, 2, 5000)
+            });
+            while !RECONFIGURING.lock().contains(&session_id) {
+                std::thread::yield_now();
+            }
+            let new_call = std::thread::spawn(move || {
+                get_processor(session_id).unwrap().get_config().redundancy_factor
+            });
+            std::thread::sleep(Duration::from_millis(50));
+            drop(running);
+
+            assert_eq!(reconfigure.join().unwrap(), 0);
+            assert_eq!(new_call.join().unwrap(), 6);
+
+            raptorq_free_session(session_id);
+        }
+
+        // Tests for raptorq_get_session_config
+        #[test]
+        fn test_ffi_get_session_config() {
+            let session_id = init_test_session();
+            let (mut symbol_size, mut redundancy_factor, mut max_memory_mb, mut concurrency_limit) = (0u16, 0u8, 0u64, 0u64);
+
+            let result = unsafe {
+                raptorq_get_session_config(
+                    session_id, &mut symbol_size, &mut redundancy_factor, &mut max_memory_mb, &mut concurrency_limit,
+                )
+            };
+            assert_eq!(result, 0);
+            assert_eq!((symbol_size, redundancy_factor, max_memory_mb, concurrency_limit), (1024, 10, 1024, 4));
+
+            // Reflects a reconfiguration
+            assert_eq!(raptorq_reconfigure_session_timeout(session_id, 2048, 6, 512, 2, 1000), 0);
+            let result = unsafe {
+                raptorq_get_session_config(
+                    session_id, &mut symbol_size, &mut redundancy_factor, &mut max_memory_mb, &mut concurrency_limit,
+                )
+            };
+            assert_eq!(result, 0);
+            assert_eq!((symbol_size, redundancy_factor, max_memory_mb, concurrency_limit), (2048, 6, 512, 2));
+
+            // Null out-pointers are rejected
+            let result = unsafe {
+                raptorq_get_session_config(
+                    session_id, ptr::null_mut(), &mut redundancy_factor, &mut max_memory_mb, &mut concurrency_limit,
+                )
+            };
+            assert_eq!(result, -2);
+
+            raptorq_free_session(session_id);
+        }
+
         #[test]
-        fn test_ffi_free_invalid_session() {
-            // Try to free a session that doesn't exist
-            let invalid_session_id = 99999;
-            let result = raptorq_free_session(invalid_session_id);
-            
-            assert!(!result, "Freeing invalid session should return false");
+        fn test_ffi_get_session_config_invalid_session() {
+            let (mut symbol_size, mut redundancy_factor, mut max_memory_mb, mut concurrency_limit) = (0u16, 0u8, 0u64, 0u64);
+            let result = unsafe {
+                raptorq_get_session_config(
+                    99999, &mut symbol_size, &mut redundancy_factor, &mut max_memory_mb, &mut concurrency_limit,
+                )
+            };
+            assert_eq!(result, -5);
         }
-    
+
+        // Tests for raptorq_shutdown_session
         #[test]
-        fn test_ffi_free_double_free() {
+        fn test_ffi_shutdown_session() {
             let session_id = init_test_session();
-            
-            // First free (should succeed)
-            let first_result = raptorq_free_session(session_id);
-            assert!(first_result, "First free should return true");
-            
-            // Second free (should fail)
-            let second_result = raptorq_free_session(session_id);
-            assert!(!second_result, "Second free of same ID should return false");
+
+            let result = raptorq_shutdown_session(session_id, 100);
+            assert_eq!(result, 0, "Shutting down an idle session should succeed");
+
+            // The session no longer accepts tasks
+            let mut result_buffer = [0u8; 1024];
+            let result = raptorq_encode_file(
+                    session_id,
+                    CString::new("input").unwrap().as_ptr(),
+                    CString::new("output").unwrap().as_ptr(),
+                    0,
+                    result_buffer.as_mut_ptr() as *mut c_char,
+                    result_buffer.len(),
+                );
+            assert_eq!(result, -17, "Shut down session should reject new tasks");
+
+            // Clean up
+            raptorq_free_session(session_id);
         }
-    
+
+        #[test]
+        fn test_ffi_shutdown_waits_for_encode_in_flight() {
+            let session_id = raptorq_init_session(8192, 2, 1024, 4);
+            let other_session_id = init_test_session();
+            let temp_dir = tempdir().expect("Failed to create temp directory");
+            let input_path = temp_dir.path().join("input.bin");
+            let data: Vec<u8> = (0..4 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+            fs::write(&input_path, &data).unwrap();
+
+            let input = CString::new(input_path.to_string_lossy().as_ref()).unwrap();
+            let output = CString::new(temp_dir.path().join("symbols").to_string_lossy().as_ref()).unwrap();
+            let encoder = std::thread::spawn(move || {
+                let mut result_buffer = [0u8; 4096];
+                raptorq_encode_file(
+                    session_id,
+                    input.as_ptr(),
+                    output.as_ptr(),
+                    0,
+                    result_buffer.as_mut_ptr() as *mut c_char,
+                    result_buffer.len(),
+                )
+            });
+
+            let processor = get_processor(session_id).unwrap();
+            while processor.active_task_count() == 0 {
+                assert!(!encoder.is_finished(), "Encoding finished before it was seen running");
+                std::thread::yield_now();
+            }
+
+            // The running encode is seen, and other sessions stay usable meanwhile
+            assert_eq!(raptorq_shutdown_session(session_id, 0), -18);
+            assert_eq!(raptorq_get_recommended_block_size(other_session_id, 1024), 0);
+            assert_eq!(raptorq_shutdown_session(session_id, 60_000), 0);
+            assert_eq!(processor.active_task_count(), 0);
+            assert_eq!(encoder.join().unwrap(), 0, "The encode in flight should complete");
+
+            raptorq_free_session(session_id);
+            raptorq_free_session(other_session_id);
+        }
+
+        #[test]
+        fn test_ffi_shutdown_invalid_session() {
+            let result = raptorq_shutdown_session(99999, 100);
+            assert_eq!(result, -5, "Invalid session ID should return -5");
+        }
+
         // Tests for raptorq_encode_file
         #[test]
         fn test_ffi_encode_null_pointers() {
@@ -780,6 +1545,179 @@ mod ffi_tests {
             raptorq_free_session(session_id);
         }
     
+        // Tests for raptorq_encode_buffer
+        #[test]
+        fn test_ffi_encode_buffer_null_pointers() {
+            let session_id = init_test_session();
+            let data = b"buffer content";
+            let output = CString::new("output").unwrap();
+            let mut result_buffer = [0u8; 1024];
+
+            let result = unsafe {
+                raptorq_encode_buffer(
+                    session_id,
+                    ptr::null(),
+                    data.len(),
+                    output.as_ptr(),
+                    0,
+                    result_buffer.as_mut_ptr() as *mut c_char,
+                    result_buffer.len(),
+                )
+            };
+            assert_eq!(result, -2, "Null data should return -2");
+
+            let result = unsafe {
+                raptorq_encode_buffer(
+                    session_id,
+                    data.as_ptr(),
+                    data.len(),
+                    ptr::null(),
+                    0,
+                    result_buffer.as_mut_ptr() as *mut c_char,
+                    result_buffer.len(),
+                )
+            };
+            assert_eq!(result, -2, "Null output_dir should return -2");
+
+            let result = unsafe {
+                raptorq_encode_buffer(
+                    session_id,
+                    data.as_ptr(),
+                    data.len(),
+                    output.as_ptr(),
+                    0,
+                    ptr::null_mut(),
+                    1024,
+                )
+            };
+            assert_eq!(result, -2, "Null result_buffer should return -2");
+
+            // Clean up
+            raptorq_free_session(session_id);
+        }
+
+        #[test]
+        fn test_ffi_encode_buffer_invalid_session() {
+            let data = b"buffer content";
+            let output = CString::new("output").unwrap();
+            let mut result_buffer = [0u8; 1024];
+
+            let result = unsafe {
+                raptorq_encode_buffer(
+                    99999,
+                    data.as_ptr(),
+                    data.len(),
+                    output.as_ptr(),
+                    0,
+                    result_buffer.as_mut_ptr() as *mut c_char,
+                    result_buffer.len(),
+                )
+            };
+
+            assert_eq!(result, -5, "Invalid session ID should return -5");
+        }
+
+        #[test]
+        fn test_ffi_encode_buffer_empty() {
+            let session_id = init_test_session();
+            let temp_dir = tempdir().expect("Failed to create temp directory");
+            let output = CString::new(temp_dir.path().to_string_lossy().as_ref()).unwrap();
+            let data: [u8; 0] = [];
+            let mut result_buffer = [0u8; 1024];
+
+            let result = unsafe {
+                raptorq_encode_buffer(
+                    session_id,
+                    data.as_ptr(),
+                    0,
+                    output.as_ptr(),
+                    0,
+                    result_buffer.as_mut_ptr() as *mut c_char,
+                    result_buffer.len(),
+                )
+            };
+
+            assert_eq!(result, -14, "Empty buffer should return -14");
+
+            // Clean up
+            raptorq_free_session(session_id);
+        }
+
+        #[test]
+        fn test_ffi_encode_buffer_success() {
+            let session_id = init_test_session();
+            let temp_dir = tempdir().expect("Failed to create temp directory");
+
+            let output_dir = temp_dir.path().join("output");
+            fs::create_dir_all(&output_dir).expect("Failed to create output directory");
+            let output = CString::new(output_dir.to_string_lossy().as_ref()).unwrap();
+
+            let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+            let mut result_buffer = [0u8; 4096];
+
+            let result = unsafe {
+                raptorq_encode_buffer(
+                    session_id,
+                    data.as_ptr(),
+                    data.len(),
+                    output.as_ptr(),
+                    0, // auto block size
+                    result_buffer.as_mut_ptr() as *mut c_char,
+                    result_buffer.len(),
+                )
+            };
+
+            assert_eq!(result, 0, "Encoding should succeed with return code 0");
+
+            let result_json = buffer_as_string(result_buffer.as_ptr() as *const c_char, result_buffer.len());
+            let parsed: serde_json::Value = serde_json::from_str(&result_json)
+                .expect("Result buffer should contain valid JSON");
+            assert!(parsed["total_symbols_count"].as_u64().unwrap() > 0);
+
+            // The symbols and the layout must be on disk and decode back to the buffer
+            let layout_path = output_dir.join("_raptorq_layout.json");
+            assert!(layout_path.exists(), "Layout file should be written");
+
+            let restored = temp_dir.path().join("restored.bin");
+            let result = raptorq_decode_symbols(
+                session_id,
+                output.as_ptr(),
+                CString::new(restored.to_string_lossy().as_ref()).unwrap().as_ptr(),
+                CString::new(layout_path.to_string_lossy().as_ref()).unwrap().as_ptr(),
+            );
+            assert_eq!(result, 0, "Decoding the encoded buffer should succeed");
+            assert_eq!(fs::read(&restored).unwrap(), data);
+
+            // Clean up
+            raptorq_free_session(session_id);
+        }
+
+        #[test]
+        fn test_ffi_encode_buffer_result_buffer_too_small() {
+            let session_id = init_test_session();
+            let temp_dir = tempdir().expect("Failed to create temp directory");
+            let output = CString::new(temp_dir.path().to_string_lossy().as_ref()).unwrap();
+            let data = b"This is test content for encoding with a buffer that's too small";
+            let mut result_buffer = [0u8; 5];
+
+            let result = unsafe {
+                raptorq_encode_buffer(
+                    session_id,
+                    data.as_ptr(),
+                    data.len(),
+                    output.as_ptr(),
+                    0,
+                    result_buffer.as_mut_ptr() as *mut c_char,
+                    result_buffer.len(),
+                )
+            };
+
+            assert_eq!(result, -4, "Result buffer too small should return -4");
+
+            // Clean up
+            raptorq_free_session(session_id);
+        }
+
         // Tests for raptorq_get_last_error
         #[test]
         fn test_ffi_get_error_null_buffer() {
@@ -961,6 +1899,27 @@ mod ffi_tests {
             assert_eq!(result, -5, "Invalid session ID should return -5");
         }
     
+        #[test]
+        fn test_ffi_decode_concurrency_limit_reached() {
+            // A session allowed no concurrent tasks at all
+            let session_id = raptorq_init_session(1024, 10, 1024, 0);
+            let temp_dir = tempdir().expect("Failed to create temp directory");
+            let layout_path = create_temp_file(temp_dir.path(), "layout.json", br#"{"blocks": []}"#)
+                .expect("Failed to create layout file");
+
+            let result = raptorq_decode_symbols(
+                session_id,
+                CString::new(temp_dir.path().to_string_lossy().as_ref()).unwrap().as_ptr(),
+                CString::new(temp_dir.path().join("output.txt").to_string_lossy().as_ref()).unwrap().as_ptr(),
+                CString::new(layout_path.to_string_lossy().as_ref()).unwrap().as_ptr(),
+            );
+
+            assert_eq!(result, -17, "Concurrency limit reached should return -17");
+
+            // Clean up
+            raptorq_free_session(session_id);
+        }
+
         #[test]
         fn test_ffi_decode_layout_file_not_found() {
             let session_id = init_test_session();
@@ -1284,6 +2243,123 @@ mod ffi_tests {
             raptorq_free_session(session_id);
         }
         
+        // Tests for raptorq_layout_block_count and raptorq_layout_block_info
+        fn encode_for_layout_queries(dir: &Path) -> PathBuf {
+            let processor = RaptorQProcessor::new(ProcessorConfig {
+                symbol_size: 1000,
+                ..ProcessorConfig::default()
+            });
+            let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+            let result = processor.encode_buffer(&data, &dir.to_string_lossy(), 4000)
+                .expect("Encoding should succeed");
+            PathBuf::from(result.layout_file_path)
+        }
+
+        #[test]
+        fn test_ffi_layout_block_count() {
+            let temp_dir = tempdir().expect("Failed to create temp directory");
+            let layout_path = encode_for_layout_queries(temp_dir.path());
+
+            let layout_path = CString::new(layout_path.to_string_lossy().as_ref()).unwrap();
+            assert_eq!(raptorq_layout_block_count(layout_path.as_ptr()), 3);
+
+            assert_eq!(raptorq_layout_block_count(ptr::null()), -2);
+            let missing = CString::new(temp_dir.path().join("missing.json").to_string_lossy().as_ref()).unwrap();
+            assert_eq!(raptorq_layout_block_count(missing.as_ptr()), -12);
+
+            let not_a_layout = create_temp_file(temp_dir.path(), "bad.json", b"not json").unwrap();
+            let not_a_layout = CString::new(not_a_layout.to_string_lossy().as_ref()).unwrap();
+            assert_eq!(raptorq_layout_block_count(not_a_layout.as_ptr()), -15);
+        }
+
+        #[test]
+        fn test_ffi_layout_block_info() {
+            let temp_dir = tempdir().expect("Failed to create temp directory");
+            let layout_path = encode_for_layout_queries(temp_dir.path());
+            let layout_json: serde_json::Value = serde_json::from_slice(&fs::read(&layout_path).unwrap()).unwrap();
+            let layout_path = CString::new(layout_path.to_string_lossy().as_ref()).unwrap();
+
+            let mut info = std::mem::MaybeUninit::<BlockInfoC>::uninit();
+            assert_eq!(unsafe { raptorq_layout_block_info(layout_path.as_ptr(), 2, info.as_mut_ptr()) }, 0);
+            let info = unsafe { info.assume_init() };
+
+            let block = &layout_json["blocks"][2];
+            assert_eq!(info.block_id, 2);
+            assert_eq!(info.original_offset, 8000);
+            assert_eq!(info.size, 2000);
+            assert_eq!(info.symbols_count, block["symbols"].as_array().unwrap().len() as u64);
+            let hash = unsafe { CStr::from_ptr(info.hash.as_ptr()) }.to_str().unwrap();
+            assert_eq!(hash, block["hash"].as_str().unwrap());
+
+            let mut info = std::mem::MaybeUninit::<BlockInfoC>::uninit();
+            assert_eq!(unsafe { raptorq_layout_block_info(layout_path.as_ptr(), 3, info.as_mut_ptr()) }, -2);
+            assert_eq!(unsafe { raptorq_layout_block_info(layout_path.as_ptr(), 0, ptr::null_mut()) }, -2);
+        }
+
+        #[test]
+        fn test_ffi_decoded_size() {
+            let session_id = init_test_session();
+            let temp_dir = tempdir().expect("Failed to create temp directory");
+
+            // Three blocks, the last one short
+            let layout_path = encode_for_layout_queries(&temp_dir.path().join("multi"));
+            let layout_path = CString::new(layout_path.to_string_lossy().as_ref()).unwrap();
+            assert_eq!(raptorq_decoded_size(session_id, layout_path.as_ptr()), 10_000);
+
+            let processor = RaptorQProcessor::new(ProcessorConfig::default());
+            let data: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+            let single_dir = temp_dir.path().join("single");
+            let result = processor.encode_buffer(&data, &single_dir.to_string_lossy(), 0)
+                .expect("Encoding should succeed");
+            let layout_path = CString::new(result.layout_file_path).unwrap();
+            assert_eq!(raptorq_decoded_size(session_id, layout_path.as_ptr()), 5000);
+
+            // Errors all return -1, with the reason kept as the session's last error
+            assert_eq!(raptorq_decoded_size(session_id, ptr::null()), -1);
+            let missing = CString::new(temp_dir.path().join("missing.json").to_string_lossy().as_ref()).unwrap();
+            assert_eq!(raptorq_decoded_size(session_id, missing.as_ptr()), -1);
+            assert_eq!(get_processor(session_id).unwrap().get_last_error(), "Layout file not found");
+            assert_eq!(raptorq_decoded_size(999_999, layout_path.as_ptr()), -1);
+
+            raptorq_free_session(session_id);
+        }
+
+        // Tests for write_cstring_to_buffer
+        #[test]
+        fn test_write_cstring_to_buffer() {
+            let mut buffer = [0x7f as c_char; 8];
+
+            assert_eq!(write_cstring_to_buffer("abc", buffer.as_mut_ptr(), buffer.len()), 0);
+            assert_eq!(buffer_as_string(buffer.as_ptr(), buffer.len()), "abc");
+
+            // Seven bytes and the terminator fill the buffer exactly
+            assert_eq!(write_cstring_to_buffer("1234567", buffer.as_mut_ptr(), buffer.len()), 0);
+            assert_eq!(buffer_as_string(buffer.as_ptr(), buffer.len()), "1234567");
+        }
+
+        #[test]
+        fn test_write_cstring_to_buffer_rejects_bad_input() {
+            let mut buffer = [0x7f as c_char; 8];
+
+            // One byte too many for the terminator, and an embedded NUL: nothing is written
+            assert_eq!(write_cstring_to_buffer("12345678", buffer.as_mut_ptr(), buffer.len()), -4);
+            assert_eq!(write_cstring_to_buffer("ab\0cd", buffer.as_mut_ptr(), buffer.len()), -3);
+            assert_eq!(write_cstring_to_buffer("", buffer.as_mut_ptr(), 0), -4);
+            assert!(buffer.iter().all(|&byte| byte == 0x7f));
+        }
+
+        #[test]
+        fn test_ffi_get_error_empty_buffer() {
+            let session_id = init_test_session();
+            let mut error_buffer = [0 as c_char; 1];
+
+            // A zero length is rejected rather than underflowing the truncation
+            let result = raptorq_get_last_error(session_id, error_buffer.as_mut_ptr(), 0);
+            assert_eq!(result, -1);
+
+            raptorq_free_session(session_id);
+        }
+
         // Tests for raptorq_get_recommended_block_size
         #[test]
         fn test_ffi_block_size_invalid_session() {
@@ -1322,7 +2398,94 @@ mod ffi_tests {
             // Clean up
             raptorq_free_session(session_id);
         }
+
+        #[test]
+        fn test_ffi_block_size_zero_means_no_split() {
+            // 1 GB of memory: files that fit are encoded whole, larger ones are split
+            let session_id = init_test_session();
+            let expected = {
+                let processors = PROCESSORS.lock();
+                let processor = processors.get(&session_id).unwrap();
+                (processor.get_recommended_block_size(1024 * 1024), processor.get_recommended_block_size(4 * 1024 * 1024 * 1024))
+            };
+
+            assert_eq!(raptorq_get_recommended_block_size(session_id, 1024 * 1024), 0);
+            let block_size = raptorq_get_recommended_block_size(session_id, 4 * 1024 * 1024 * 1024);
+            assert!(block_size > 0 && block_size < 4 * 1024 * 1024 * 1024);
+            assert_eq!((0, block_size), expected, "The FFI should return what the processor recommends");
+
+            raptorq_free_session(session_id);
+        }
         
+        // Tests for raptorq_verify_symbol
+        #[test]
+        fn test_ffi_verify_symbol_null_pointers() {
+            let data = b"symbol";
+            let expected_id = CString::new("id").unwrap();
+
+            assert_eq!(unsafe { raptorq_verify_symbol(ptr::null(), 0, expected_id.as_ptr()) }, -2);
+            assert_eq!(unsafe { raptorq_verify_symbol(data.as_ptr(), data.len(), ptr::null()) }, -2);
+        }
+
+        #[test]
+        fn test_ffi_verify_symbol() {
+            let processor = RaptorQProcessor::new(ProcessorConfig::default());
+            let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+            let (layout, symbols) = processor.encode_bytes_to_map(&data, 0)
+                .expect("Encoding to the map should succeed");
+
+            let symbol_id = &layout.blocks[0].symbols[0];
+            let expected_id = CString::new(symbol_id.as_str()).unwrap();
+            let packet = &symbols[symbol_id];
+            assert_eq!(unsafe { raptorq_verify_symbol(packet.as_ptr(), packet.len(), expected_id.as_ptr()) }, 1);
+
+            let mut corrupted = packet.clone();
+            corrupted[0] ^= 0xFF;
+            assert_eq!(unsafe { raptorq_verify_symbol(corrupted.as_ptr(), corrupted.len(), expected_id.as_ptr()) }, 0);
+        }
+
+        #[test]
+        fn test_ffi_verify_symbol_with_hash() {
+            let processor = RaptorQProcessor::new(ProcessorConfig {
+                hash_algorithm: HashAlgorithm::Sha256,
+                ..ProcessorConfig::default()
+            });
+            let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+            let (layout, symbols) = processor.encode_bytes_to_map(&data, 0)
+                .expect("Encoding to the map should succeed");
+
+            let symbol_id = &layout.blocks[0].symbols[0];
+            let expected_id = CString::new(symbol_id.as_str()).unwrap();
+            let packet = &symbols[symbol_id];
+            let verify = |data: &[u8], hash: &str| {
+                let hash = CString::new(hash).unwrap();
+                unsafe { raptorq_verify_symbol_with_hash(data.as_ptr(), data.len(), expected_id.as_ptr(), hash.as_ptr()) }
+            };
+            assert_eq!(verify(packet, "sha256"), 1);
+            assert_eq!(verify(packet, "blake3"), 0);
+            assert_eq!(verify(packet, "md5"), -2, "An unknown hash should be rejected");
+
+            let mut corrupted = packet.clone();
+            corrupted[0] ^= 0xFF;
+            assert_eq!(verify(&corrupted, "sha256"), 0);
+
+            // The BLAKE3-only check can't vouch for SHA-256 IDs
+            assert_eq!(unsafe { raptorq_verify_symbol(packet.as_ptr(), packet.len(), expected_id.as_ptr()) }, 0);
+            assert_eq!(unsafe { raptorq_verify_symbol_with_hash(packet.as_ptr(), packet.len(), expected_id.as_ptr(), ptr::null()) }, -2);
+        }
+
+        // Tests for raptorq_packet_header_size
+        #[test]
+        fn test_ffi_packet_header_size() {
+            let symbol_size = 1024u16;
+            let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+            let encoder = raptorq::Encoder::with_defaults(&data, symbol_size);
+            let packet = &encoder.get_encoded_packets(1)[0];
+
+            assert_eq!(raptorq_packet_header_size(), packet.serialize().len() - symbol_size as usize);
+            assert_eq!(raptorq_packet_header_size(), PACKET_HEADER_SIZE_B);
+        }
+
         // Tests for raptorq_version
         #[test]
         fn test_ffi_version_null_buffer() {
@@ -1359,6 +2522,24 @@ mod ffi_tests {
             assert!(version_str.contains("RaptorQ Library"), "Version string should contain library name");
         }
     
+        #[test]
+        fn test_ffi_engine_version() {
+            let mut version_buffer = [0u8; 64];
+
+            let result = raptorq_engine_version(
+                version_buffer.as_mut_ptr() as *mut c_char,
+                version_buffer.len(),
+            );
+
+            assert_eq!(result, 0, "Valid buffer should return 0");
+            let version_str = buffer_as_string(version_buffer.as_ptr() as *const c_char, version_buffer.len());
+            assert_eq!(version_str, processor::raptorq_engine_version());
+
+            let mut small_buffer = [0u8; 2];
+            let result = raptorq_engine_version(small_buffer.as_mut_ptr() as *mut c_char, small_buffer.len());
+            assert_eq!(result, -1, "Buffer too small should return -1");
+        }
+
     fn init_test_session() -> usize {
         // Using reasonable default values for testing
         raptorq_init_session(1024, 10, 1024, 4)
@@ -0,0 +1,84 @@
+//! Symbol stores addressed by block and symbol ID
+//!
+//! Where the `file_io` traits see symbols as files under `block_N/` directories,
+//! these traits hand each symbol over with its block and symbol ID, so a store can
+//! keep them under keys of its own choosing:
+//! - `SymbolSink`: Where `encode_bytes_to_store` puts the symbols as they are produced
+//! - `SymbolSource`: Where `decode_from_store` fetches them from
+//!
+//! `object_store` (behind the `object-store` feature) implements both over any
+//! store of the `object_store` crate, mapping symbols to keys through a template.
+#[cfg(all(not(target_arch = "wasm32"), feature = "object-store"))]
+pub mod object_store;
+
+use crate::processor::{BlockId, SymbolId};
+
+/// Trait for storing the serialized symbols of an encode.
+///
+/// Blocks may be encoded in parallel, so symbols can be put from several threads at once.
+pub trait SymbolSink: Sync {
+    /// Stores the serialized packet of a symbol of the given block.
+    fn put_symbol(&self, block_id: BlockId, symbol_id: &SymbolId, packet: &[u8]) -> Result<(), String>;
+}
+
+/// Trait for fetching serialized symbols back for decoding.
+pub trait SymbolSource: Sync {
+    /// Returns the serialized packet of a symbol of the given block, or None if
+    /// the store doesn't have it.
+    fn get_symbol(&self, block_id: BlockId, symbol_id: &SymbolId) -> Result<Option<Vec<u8>>, String>;
+
+    /// Returns the contents of the symbol manifest of the given block, or None if the
+    /// store doesn't have it. Only layouts encoded with `external_symbol_manifests`
+    /// reference one, at `path` relative to the symbols directory (e.g.
+    /// `block_0/symbols.list`). The default has no manifests.
+    fn get_manifest(&self, _block_id: BlockId, _path: &str) -> Result<Option<Vec<u8>>, String> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(all(not(target_arch = "wasm32"), feature = "object-store"))]
+    #[test]
+    fn test_object_store_round_trip() {
+        use super::object_store::ObjectStoreSymbols;
+        use super::SymbolSource;
+        use crate::processor::{ProcessorConfig, RaptorQProcessor};
+        use ::object_store::memory::InMemory;
+        use ::object_store::path::Path;
+        use ::object_store::ObjectStore;
+        use std::sync::Arc;
+
+        let memory = Arc::new(InMemory::new());
+        let store = ObjectStoreSymbols::new(memory.clone(), "prefix/{block_id}/{symbol_id}").unwrap();
+
+        let processor = RaptorQProcessor::new(ProcessorConfig {
+            symbol_size: 1000,
+            ..ProcessorConfig::default()
+        });
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let layout = processor.encode_bytes_to_store(&data, 4000, &store).unwrap();
+        assert_eq!(layout.blocks.len(), 3);
+
+        // Every symbol lands under its templated key
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        for block in &layout.blocks {
+            for symbol_id in &block.symbols {
+                let key = Path::from(format!("prefix/{}/{}", block.block_id, symbol_id));
+                let packet = runtime.block_on(async { memory.get(&key).await?.bytes().await }).unwrap();
+                assert_eq!(store.get_symbol(block.block_id, symbol_id).unwrap().as_deref(), Some(&packet[..]));
+            }
+        }
+
+        assert_eq!(processor.decode_from_store(&layout, &store).unwrap(), data);
+
+        // Missing symbols are skipped, and decoding carries on with the others
+        let block = &layout.blocks[0];
+        let key = Path::from(format!("prefix/{}/{}", block.block_id, block.symbols[0]));
+        runtime.block_on(memory.delete(&key)).unwrap();
+        assert_eq!(store.get_symbol(block.block_id, &block.symbols[0]).unwrap(), None);
+        assert_eq!(processor.decode_from_store(&layout, &store).unwrap(), data);
+
+        assert!(ObjectStoreSymbols::new(memory, "prefix/{block_id}").is_err(), "A template must name the symbol");
+    }
+}
@@ -0,0 +1,85 @@
+//! SymbolSink and SymbolSource over a store of the `object_store` crate.
+//!
+//! Each symbol is kept under the key its template gives it: `{block_id}` is
+//! replaced with the block's index and `{symbol_id}` with the symbol ID, so
+//! `"prefix/{block_id}/{symbol_id}"` spreads the blocks over their own prefixes.
+//! A block's symbol manifest is kept like a symbol named after the manifest file,
+//! e.g. `prefix/0/symbols.list`.
+//!
+//! `object_store` is async; its futures are run to completion on a runtime the
+//! store owns, so the blocking traits can be called from any thread but not from
+//! within an async task.
+
+use std::sync::Arc;
+
+use ::object_store::path::Path;
+use ::object_store::{ObjectStore, PutPayload};
+use tokio::runtime::Runtime;
+
+use super::{SymbolSink, SymbolSource};
+use crate::processor::{BlockId, SymbolId};
+
+/// SymbolSink and SymbolSource keeping symbols in an `ObjectStore` under templated keys.
+pub struct ObjectStoreSymbols {
+    store: Arc<dyn ObjectStore>,
+    key_template: String,
+    runtime: Runtime,
+}
+
+impl ObjectStoreSymbols {
+    /// Wraps `store`, keeping symbols under `key_template`, which must contain `{symbol_id}`.
+    pub fn new(store: Arc<dyn ObjectStore>, key_template: &str) -> Result<Self, String> {
+        if !key_template.contains("{symbol_id}") {
+            return Err(format!("The key template doesn't contain {{symbol_id}}: {}", key_template));
+        }
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("Failed to start the object store runtime: {}", e))?;
+        Ok(Self { store, key_template: key_template.to_string(), runtime })
+    }
+
+    /// The key a symbol is kept under.
+    pub fn key_for(&self, block_id: BlockId, symbol_id: &SymbolId) -> Result<Path, String> {
+        let key = self.key_template
+            .replace("{block_id}", &block_id.to_string())
+            .replace("{symbol_id}", symbol_id.as_str());
+        Path::parse(&key).map_err(|e| format!("Invalid object key {}: {}", key, e))
+    }
+}
+
+impl SymbolSink for ObjectStoreSymbols {
+    fn put_symbol(&self, block_id: BlockId, symbol_id: &SymbolId, packet: &[u8]) -> Result<(), String> {
+        let key = self.key_for(block_id, symbol_id)?;
+        self.runtime.block_on(self.store.put(&key, PutPayload::from(packet.to_vec())))
+            .map(|_| ())
+            .map_err(|e| format!("Failed to put {}: {}", key, e))
+    }
+}
+
+impl ObjectStoreSymbols {
+    // The object at `key`, None if there is none
+    fn get(&self, key: &Path) -> Result<Option<Vec<u8>>, String> {
+        let result = self.runtime.block_on(async {
+            self.store.get(key).await?.bytes().await
+        });
+        match result {
+            Ok(bytes) => Ok(Some(bytes.to_vec())),
+            Err(::object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(format!("Failed to get {}: {}", key, e)),
+        }
+    }
+}
+
+impl SymbolSource for ObjectStoreSymbols {
+    fn get_symbol(&self, block_id: BlockId, symbol_id: &SymbolId) -> Result<Option<Vec<u8>>, String> {
+        let key = self.key_for(block_id, symbol_id)?;
+        self.get(&key)
+    }
+
+    fn get_manifest(&self, block_id: BlockId, path: &str) -> Result<Option<Vec<u8>>, String> {
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        let key = self.key_for(block_id, &SymbolId::from(file_name))?;
+        self.get(&key)
+    }
+}
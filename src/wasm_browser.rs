@@ -5,15 +5,16 @@ pub mod browser_wasm {
     use wasm_bindgen::prelude::*;
     use js_sys::{Uint8Array, Promise, Object};
     use wasm_bindgen_futures::future_to_promise;
-    use crate::processor::{ProcessorConfig, RaptorQProcessor};
+    use crate::processor::{EncodeOptions, ProcessorConfig, RaptorQProcessor};
     use serde::Serialize;
     use std::sync::Arc;
 
     // Import the new file I/O abstractions
-    use crate::file_io::{get_dir_manager, open_file_reader};
+    use crate::file_io::{get_dir_manager, open_file_reader, read_full_chunk_async, AsyncFileReader};
+    use crate::processor::RaptorQLayout;
     
     // Import helpers from file_io/wasm module
-    use crate::file_io::wasm::log_to_console;
+    use crate::file_io::wasm::{log_to_console, BrowserFileReader, BrowserFileWriter};
 
     // Initialize panic hook for better error messages
     #[wasm_bindgen(start)]
@@ -37,6 +38,7 @@ pub mod browser_wasm {
                 redundancy_factor,
                 max_memory_mb,
                 concurrency_limit,
+                ..ProcessorConfig::default()
             };
 
             let processor = RaptorQProcessor::new(config);
@@ -84,18 +86,18 @@ pub mod browser_wasm {
                 dir_manager.create_dir_all(&output_dir)
                     .map_err(|e| JsValue::from_str(&format!("Failed to create directory: {}", e)))?;
 
-                // Get file size using the new FileReader trait
-                let reader = open_file_reader(&input_path)
-                    .map_err(|e| JsValue::from_str(&format!("Failed to open file: {}", e)))?;
-                let file_size = reader.file_size()
-                    .map_err(|e| JsValue::from_str(&format!("Failed to get file size: {}", e)))?;
-
-                // Calculate actual block size
-                let actual_block_size = if block_size == 0 { processor.get_recommended_block_size(file_size as usize) } else { block_size };
-
-                // Use generic encode_file method instead of browser-specific versions
+                // Read and write through the async file I/O, awaiting the JS promises;
+                // a block size of 0 picks the recommended one
+                let mut reader = BrowserFileReader::new(&input_path);
                 let result = processor
-                    .encode_file(&input_path, &output_dir, actual_block_size, false)
+                    .encode_async(
+                        &mut reader,
+                        &output_dir,
+                        dir_manager.as_ref(),
+                        |path: &str| Ok::<_, String>(BrowserFileWriter::new(path)),
+                        EncodeOptions { block_size, ..EncodeOptions::default() },
+                    )
+                    .await
                     .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
                 Ok(build_result_object(&result)?)
@@ -108,8 +110,24 @@ pub mod browser_wasm {
             let processor = self.processor.clone();
 
             future_to_promise(async move {
-                // Use generic decode_symbols method
-                match processor.decode_symbols(&symbols_dir, &output_path, &layout_path) {
+                // Read the layout through the async file I/O
+                let mut layout_reader = BrowserFileReader::new(&layout_path);
+                let layout_size = AsyncFileReader::file_size(&layout_reader)
+                    .map_err(|e| JsValue::from_str(&format!("Failed to get the layout file size: {}", e)))?;
+                let mut layout_bytes = vec![0u8; layout_size as usize];
+                read_full_chunk_async(&mut layout_reader, 0, &mut layout_bytes).await
+                    .map_err(|e| JsValue::from_str(&format!("Failed to read the layout file: {}", e)))?;
+                let layout: RaptorQLayout = serde_json::from_slice(&layout_bytes)
+                    .map_err(|e| JsValue::from_str(&format!("Failed to parse the layout file: {}", e)))?;
+
+                let mut output = BrowserFileWriter::new(&output_path);
+                match processor.decode_async(
+                    &symbols_dir,
+                    &layout,
+                    get_dir_manager().as_ref(),
+                    |path: &str| Ok::<_, String>(BrowserFileReader::new(path)),
+                    &mut output,
+                ).await {
                     Ok(_) => {},
                     Err(e) => return Err(JsValue::from_str(&format!("Error decoding symbols: {}", e))),
                 };
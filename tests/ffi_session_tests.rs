@@ -0,0 +1,38 @@
+//! Tests for the FFI session cap.
+//!
+//! The cap is global to the process, so these tests live in their own test binary
+//! where no other test opens sessions concurrently.
+
+use rq_library::{raptorq_free_session, raptorq_init_session, raptorq_set_max_sessions};
+
+#[test]
+fn test_ffi_max_sessions() {
+    raptorq_set_max_sessions(2);
+
+    let first = raptorq_init_session(1024, 10, 1024, 4);
+    let second = raptorq_init_session(1024, 10, 1024, 4);
+    assert!(first > 0 && second > 0, "Sessions up to the cap should be created");
+    assert_eq!(raptorq_init_session(1024, 10, 1024, 4), 0, "Init past the cap should fail");
+
+    // Freeing a session makes room for another
+    assert!(raptorq_free_session(first));
+    let third = raptorq_init_session(1024, 10, 1024, 4);
+    assert!(third > 0, "Init should succeed once a session is freed");
+    assert_ne!(third, first);
+    assert_eq!(raptorq_init_session(1024, 10, 1024, 4), 0);
+
+    // Lowering the cap keeps open sessions but blocks new ones
+    raptorq_set_max_sessions(1);
+    assert_eq!(raptorq_init_session(1024, 10, 1024, 4), 0);
+    assert!(raptorq_free_session(second));
+    assert_eq!(raptorq_init_session(1024, 10, 1024, 4), 0);
+
+    // 0 removes the cap
+    raptorq_set_max_sessions(0);
+    let sessions: Vec<usize> = (0..4).map(|_| raptorq_init_session(1024, 10, 1024, 4)).collect();
+    assert!(sessions.iter().all(|&id| id > 0), "Init should succeed without a cap");
+
+    for id in sessions.into_iter().chain([third]) {
+        assert!(raptorq_free_session(id));
+    }
+}
@@ -93,4 +93,41 @@ fn test_dir_manager_create() {
     let nested_str = nested.to_string_lossy();
     dir_manager.create_dir_all(&nested_str).unwrap();
     assert!(nested.exists());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_dir_manager_list_files() {
+    let dir_manager = NativeDirManager;
+    let tmp_dir = tempfile::tempdir().unwrap();
+    std::fs::write(tmp_dir.path().join("one"), b"1").unwrap();
+    std::fs::write(tmp_dir.path().join("two"), b"2").unwrap();
+    std::fs::create_dir(tmp_dir.path().join("sub")).unwrap();
+
+    let mut names = dir_manager.list_files(&tmp_dir.path().to_string_lossy()).unwrap();
+    names.sort();
+    assert_eq!(names, vec!["one".to_string(), "two".to_string()]);
+}
+
+#[test]
+fn test_dir_manager_optional_methods_default_to_unsupported() {
+    // Implementors written before list_files, remove_file and rename_file still compile
+    struct CreateOnlyDirManager;
+    impl DirManager for CreateOnlyDirManager {
+        fn create_dir_all(&self, _path: &str) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn dir_exists(&self, _path: &str) -> Result<bool, String> {
+            Ok(true)
+        }
+
+        fn count_files(&self, _path: &str) -> Result<usize, String> {
+            Ok(0)
+        }
+    }
+
+    let dir_manager = CreateOnlyDirManager;
+    assert!(dir_manager.list_files("dir").is_err());
+    assert!(dir_manager.remove_file("dir/file").is_err());
+    assert!(dir_manager.rename_file("dir/a", "dir/b").is_err());
+}
@@ -265,6 +265,7 @@ fn test_sys_encode_decode_large_file_auto_chunk() {
         redundancy_factor: 12,
         max_memory_mb: 10, // Small memory limit to force splitting
         concurrency_limit: 4,
+        ..ProcessorConfig::default()
     };
     
     let processor = RaptorQProcessor::new(config);
@@ -580,6 +581,7 @@ fn test_sys_create_metadata_medium_file_auto_chunk() {
         redundancy_factor: 6,
         max_memory_mb: 1, // Small memory limit to force splitting
         concurrency_limit: 4,
+        ..ProcessorConfig::default()
     };
     
     let processor = RaptorQProcessor::new(config);